@@ -0,0 +1,51 @@
+use termix::{
+    event::Event,
+    key::Key,
+    model::{Cmd, ModelAct, Updater},
+    options::ProgramOptions,
+    Program, TermixError,
+};
+
+struct Model {
+    alt_screen: bool,
+}
+
+impl ModelAct<Model, ()> for Model {
+    fn update(&self, event: &Event<()>) -> Updater<Model, ()> {
+        match event {
+            Event::Keyboard(Key::Char(' ')) => {
+                let cmd = if self.alt_screen {
+                    Cmd::exit_alt_screen()
+                } else {
+                    Cmd::enter_alt_screen()
+                };
+                (
+                    Some(Box::new(Model {
+                        alt_screen: !self.alt_screen,
+                    })),
+                    Some(cmd),
+                )
+            }
+            Event::Keyboard(Key::ESC | Key::Char('q') | Key::Ctrl('c')) => {
+                (None, Some(Cmd::quit()))
+            }
+            _ => (None, None),
+        }
+    }
+    fn view(&self) -> String {
+        let mode = if self.alt_screen {
+            "altscreen mode"
+        } else {
+            "inline mode"
+        };
+        format!("\n\n  You're in {mode}\n\n\n  space: switch modes • ctrl+z: suspend • q: exit\n")
+    }
+}
+
+fn main() -> Result<(), TermixError> {
+    let options = ProgramOptions::new()
+        .alt_screen(true)
+        .suspend_on_ctrl_z(true);
+    Program::with_options(Box::new(Model { alt_screen: true }), options).run()?;
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use termix::{
+    component::Component,
+    event::Event,
+    key::Key,
+    model::{Cmd, Model},
+    Program, TermixError,
+};
+
+/// Reusable widget counting down with its own tick event.
+struct Countdown {
+    left: u32,
+    interval: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tick;
+
+impl Countdown {
+    fn tick(&self) -> Cmd<Tick> {
+        Cmd::tick(self.interval, |_| Event::Custom(Tick))
+    }
+}
+
+impl Model<Tick> for Countdown {
+    fn update(&mut self, event: &Event<Tick>) -> Option<Cmd<Tick>> {
+        match event {
+            Event::Custom(Tick) if self.left > 0 => {
+                self.left -= 1;
+                Some(self.tick())
+            }
+            _ => None,
+        }
+    }
+    fn init(&self) -> Option<Cmd<Tick>> {
+        Some(self.tick())
+    }
+    fn view(&self) -> String {
+        format!("{:>3} {}", self.left, "#".repeat(self.left as usize))
+    }
+}
+
+#[derive(Debug)]
+enum Msg {
+    Fast(Tick),
+    Slow(Tick),
+}
+
+struct App {
+    fast: Component<Countdown, Tick, Msg>,
+    slow: Component<Countdown, Tick, Msg>,
+}
+
+impl Model<Msg> for App {
+    fn update(&mut self, event: &Event<Msg>) -> Option<Cmd<Msg>> {
+        if let Event::Keyboard(Key::ESC | Key::Char('q') | Key::Ctrl('c')) = event {
+            return Some(Cmd::quit());
+        }
+        let cmds: Vec<Cmd<Msg>> = [self.fast.update(event), self.slow.update(event)]
+            .into_iter()
+            .flatten()
+            .collect();
+        Some(Cmd::batch(cmds))
+    }
+    fn init(&self) -> Option<Cmd<Msg>> {
+        let cmds = [self.fast.init(), self.slow.init()];
+        Some(Cmd::batch(cmds.into_iter().flatten().collect()))
+    }
+    fn view(&self) -> String {
+        format!(
+            "\n  fast {}\n  slow {}\n\n  q: quit\n",
+            self.fast.view(),
+            self.slow.view()
+        )
+    }
+}
+
+fn countdown(left: u32, millis: u64) -> Countdown {
+    Countdown {
+        left,
+        interval: Duration::from_millis(millis),
+    }
+}
+
+fn main() -> Result<(), TermixError> {
+    let app = App {
+        fast: Component::new(countdown(20, 100), Msg::Fast, |msg| match msg {
+            Msg::Fast(tick) => Some(*tick),
+            _ => None,
+        }),
+        slow: Component::new(countdown(20, 300), Msg::Slow, |msg| match msg {
+            Msg::Slow(tick) => Some(*tick),
+            _ => None,
+        }),
+    };
+    Program::from_model(app).run()?;
+    Ok(())
+}
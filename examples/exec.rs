@@ -0,0 +1,50 @@
+use std::{env, io, process::Command};
+
+use termix::{
+    event::Event,
+    key::Key,
+    model::{Cmd, Model},
+    options::ProgramOptions,
+    Program, TermixError,
+};
+
+struct Editor {
+    status: String,
+}
+
+#[derive(Debug)]
+struct Closed(io::Result<std::process::ExitStatus>);
+
+impl Model<Closed> for Editor {
+    fn update(&mut self, event: &Event<Closed>) -> Option<Cmd<Closed>> {
+        match event {
+            Event::Keyboard(Key::Char('e')) => {
+                let editor = env::var("EDITOR").unwrap_or_else(|_| String::from("vi"));
+                Some(Cmd::exec(Command::new(editor), |status| {
+                    Event::Custom(Closed(status))
+                }))
+            }
+            Event::Custom(Closed(Ok(status))) => {
+                self.status = format!("Editor exited with {status}");
+                None
+            }
+            Event::Custom(Closed(Err(err))) => {
+                self.status = format!("Failed to open the editor: {err}");
+                None
+            }
+            Event::Keyboard(Key::ESC | Key::Char('q') | Key::Ctrl('c')) => Some(Cmd::quit()),
+            _ => None,
+        }
+    }
+    fn view(&self) -> String {
+        format!("\n  {}\n\n  e: open $EDITOR • q: exit\n", self.status)
+    }
+}
+
+fn main() -> Result<(), TermixError> {
+    let editor = Editor {
+        status: String::from("Press e to open your editor"),
+    };
+    Program::from_model_with_options(editor, ProgramOptions::new().alt_screen(true)).run()?;
+    Ok(())
+}
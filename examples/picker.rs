@@ -0,0 +1,79 @@
+//! Prints the chosen item to stdout, so it can be used like `vim $(cargo run --example picker)`.
+//! Items are read from stdin if it is piped: `ls | cargo run --example picker`.
+
+use std::io::{self, BufRead, IsTerminal};
+
+use termix::{
+    color::{Color, StyledText},
+    event::Event,
+    key::Key,
+    model::{Cmd, Model},
+    options::{OutputSink, ProgramOptions},
+    Program, TermixError,
+};
+
+struct Picker {
+    items: Vec<String>,
+    cursor: usize,
+    chosen: Option<usize>,
+}
+
+impl Model<()> for Picker {
+    fn update(&mut self, event: &Event<()>) -> Option<Cmd<()>> {
+        match event {
+            Event::Keyboard(Key::Down | Key::Char('j')) => {
+                self.cursor = (self.cursor + 1).min(self.items.len().saturating_sub(1));
+            }
+            Event::Keyboard(Key::Up | Key::Char('k')) => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            Event::Keyboard(Key::Enter) if !self.items.is_empty() => {
+                self.chosen = Some(self.cursor);
+                return Some(Cmd::quit());
+            }
+            Event::Keyboard(Key::ESC | Key::Char('q') | Key::Ctrl('c')) => return Some(Cmd::quit()),
+            _ => {}
+        }
+        None
+    }
+    fn view(&self) -> String {
+        if self.chosen.is_some() {
+            return String::new();
+        }
+        let mut s = String::new();
+        for (i, item) in self.items.iter().enumerate() {
+            if i == self.cursor {
+                let line = format!("> {item}");
+                s += &StyledText::new(&line, Some(Color::Ansi256(212)), None, None, None, None)
+                    .text();
+            } else {
+                s += &format!("  {item}");
+            }
+            s += "\n";
+        }
+        s
+    }
+}
+
+fn main() -> Result<(), TermixError> {
+    let stdin = io::stdin();
+    let items = if stdin.is_terminal() {
+        ["Cargo.toml", "README.md", "src/lib.rs"]
+            .map(String::from)
+            .to_vec()
+    } else {
+        stdin.lock().lines().map_while(Result::ok).collect()
+    };
+    let picker = Picker {
+        items,
+        cursor: 0,
+        chosen: None,
+    };
+    // keys are read from /dev/tty by default, so stdin can be piped as well
+    let options = ProgramOptions::new().output(OutputSink::Tty);
+    let picker = Program::from_model_with_options(picker, options).run()?;
+    if let Some(i) = picker.chosen {
+        println!("{}", picker.items[i]);
+    }
+    Ok(())
+}
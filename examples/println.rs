@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use termix::{
+    event::Event,
+    key::Key,
+    model::{Cmd, Model},
+    Program, TermixError,
+};
+
+const PACKAGES: [&str; 6] = [
+    "vegeutils",
+    "libgardening",
+    "currykit",
+    "spicerack",
+    "fullenglish",
+    "eggy",
+];
+
+struct Installer {
+    installed: usize,
+}
+
+#[derive(Debug)]
+struct Installed;
+
+fn install() -> Cmd<Installed> {
+    Cmd::tick(Duration::from_millis(400), |_| Event::Custom(Installed))
+}
+
+impl Model<Installed> for Installer {
+    fn update(&mut self, event: &Event<Installed>) -> Option<Cmd<Installed>> {
+        match event {
+            Event::Custom(Installed) => {
+                let printed = Cmd::printf(format_args!("✓ installed {}", PACKAGES[self.installed]));
+                self.installed += 1;
+                if self.installed == PACKAGES.len() {
+                    return Some(Cmd::sequence(vec![printed, Cmd::quit()]));
+                }
+                Some(Cmd::batch(vec![printed, install()]))
+            }
+            Event::Keyboard(Key::ESC | Key::Char('q') | Key::Ctrl('c')) => Some(Cmd::quit()),
+            _ => None,
+        }
+    }
+    fn init(&self) -> Option<Cmd<Installed>> {
+        Some(install())
+    }
+    fn view(&self) -> String {
+        match PACKAGES.get(self.installed) {
+            Some(name) => format!(
+                "Installing {name}... {}/{}\n",
+                self.installed + 1,
+                PACKAGES.len()
+            ),
+            None => format!("Done! Installed {} packages.\n", PACKAGES.len()),
+        }
+    }
+}
+
+fn main() -> Result<(), TermixError> {
+    Program::from_model(Installer { installed: 0 }).run()?;
+    Ok(())
+}
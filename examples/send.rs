@@ -0,0 +1,62 @@
+use std::{thread, time::Duration};
+
+use termix::{
+    event::Event,
+    model::{Cmd, Model},
+    Program, TermixError,
+};
+
+const FOODS: [&str; 5] = [
+    "an apple",
+    "a pear",
+    "a gherkin",
+    "a party gherkin",
+    "a kohlrabi",
+];
+
+struct Results {
+    results: Vec<String>,
+    changed: bool,
+}
+
+#[derive(Debug)]
+struct Ate(String);
+
+impl Model<Ate> for Results {
+    fn update(&mut self, event: &Event<Ate>) -> Option<Cmd<Ate>> {
+        self.changed = false;
+        match event {
+            Event::Custom(Ate(food)) => {
+                self.results.push(format!("Ate {food}"));
+                self.changed = true;
+                None
+            }
+            Event::Keyboard(..) => Some(Cmd::quit()),
+            _ => None,
+        }
+    }
+    fn changed(&self) -> bool {
+        self.changed
+    }
+    fn view(&self) -> String {
+        let mut s = String::from("\n  Results from a background worker:\n\n");
+        self.results.iter().for_each(|r| s += &format!("  {r}\n"));
+        s + "\n  Press any key to exit\n"
+    }
+}
+
+fn main() -> Result<(), TermixError> {
+    let program = Program::from_model(Results {
+        results: vec![],
+        changed: false,
+    });
+    let handle = program.sender();
+    thread::spawn(move || {
+        for food in FOODS {
+            thread::sleep(Duration::from_millis(500));
+            handle.send(Ate(food.to_string()));
+        }
+    });
+    program.run()?;
+    Ok(())
+}
@@ -3,8 +3,8 @@ use std::time::Duration;
 use termix::{
     color::{Color, StyledText},
     event::Event,
-    model::{ModelAct, Updater},
-    Program,
+    model::{Cmd, ModelAct, Updater},
+    Program, TermixError,
 };
 
 struct Model(usize);
@@ -15,18 +15,20 @@ struct Tick {}
 impl ModelAct<Model, Tick> for Model {
     fn update(&self, event: &Event<Tick>) -> Updater<Model, Tick> {
         match event {
-            Event::Init => (Some(Box::new(Model(self.0))), Some(tick)),
             Event::Custom(_) => {
                 let next = self.0 - 1;
                 if next == 0 {
-                    return (Some(Box::new(Model(next))), Some(|| Event::Quit));
+                    return (Some(Box::new(Model(next))), Some(Cmd::quit()));
                 }
-                (Some(Box::new(Model(next))), Some(tick))
+                (Some(Box::new(Model(next))), Some(tick()))
             }
-            Event::Keyboard(..) => (None, Some(|| Event::Quit)),
+            Event::Keyboard(..) => (None, Some(Cmd::quit())),
             _ => (None, None),
         }
     }
+    fn init(&self) -> Option<Cmd<Tick>> {
+        Some(tick())
+    }
     fn view(&self) -> String {
         StyledText::new(
             &format!(
@@ -43,12 +45,11 @@ impl ModelAct<Model, Tick> for Model {
     }
 }
 
-fn tick() -> Event<Tick> {
-    let one_sec = Duration::from_secs(1);
-    std::thread::sleep(one_sec);
-    Event::Custom(Tick {})
+fn tick() -> Cmd<Tick> {
+    Cmd::tick(Duration::from_secs(1), |_| Event::Custom(Tick {}))
 }
 
-fn main() {
-    Program::new(Box::new(Model(5))).run();
+fn main() -> Result<(), TermixError> {
+    Program::new(Box::new(Model(5))).run()?;
+    Ok(())
 }
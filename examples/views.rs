@@ -5,11 +5,14 @@ use termix::{
     color::{Color, StyledText},
     event::Event,
     key::Key,
-    model::{ModelAct, Updater},
-    Program,
+    model::{Cmd, ModelAct, Updater},
+    Program, TermixError,
 };
 
-const PROGRESS_BAR_WIDTH: u64 = 71;
+// the ramp has a color for each cell up to this width
+const MAX_PROGRESS_BAR_WIDTH: usize = 71;
+// indent and percentage around the bar
+const PROGRESS_BAR_PADDING: usize = 7;
 
 const PROGRESS_FULL_CHAR: &str = "█";
 const PROGRESS_EMPTY_CHAR: char = '░';
@@ -44,6 +47,7 @@ struct Model {
     frames: usize,
     loaded: bool,
     progress: f64,
+    cols: u16,
 }
 
 impl Model {
@@ -56,6 +60,7 @@ impl Model {
             frames: 0,
             loaded: false,
             progress: 0f64,
+            cols: 0,
         }
     }
 }
@@ -68,10 +73,22 @@ enum CustomEvent {
 
 impl ModelAct<Model, CustomEvent> for Model {
     fn update(&self, event: &Event<CustomEvent>) -> Updater<Model, CustomEvent> {
+        if let Event::Resize { cols, .. } = event {
+            return (
+                Some(Box::new(Model {
+                    cols: *cols,
+                    ..*self
+                })),
+                None,
+            );
+        }
         if !self.chosen {
             return update_choices(event, self);
         }
-        return update_chosen(event, self);
+        update_chosen(event, self)
+    }
+    fn init(&self) -> Option<Cmd<CustomEvent>> {
+        Some(tick())
     }
     fn view(&self) -> String {
         if self.quitting {
@@ -88,7 +105,6 @@ impl ModelAct<Model, CustomEvent> for Model {
 
 fn update_choices(event: &Event<CustomEvent>, model: &Model) -> Updater<Model, CustomEvent> {
     match event {
-        Event::Init => (Some(Box::new(Model { ..*model })), Some(tick)),
         Event::Keyboard(Key::Down | Key::Char('j')) => {
             let mut choice = model.choice + 1;
             if model.choice + 1 > 3 {
@@ -108,14 +124,14 @@ fn update_choices(event: &Event<CustomEvent>, model: &Model) -> Updater<Model, C
                 chosen: true,
                 ..*model
             })),
-            Some(frame),
+            Some(frame()),
         ),
         Event::Keyboard(Key::ESC | Key::Char('q') | Key::Ctrl('c')) => (
             Some(Box::new(Model {
                 quitting: true,
                 ..*model
             })),
-            Some(|| Event::Quit),
+            Some(Cmd::quit()),
         ),
         Event::Custom(CustomEvent::Tick) => {
             if model.ticks == 0 {
@@ -124,7 +140,7 @@ fn update_choices(event: &Event<CustomEvent>, model: &Model) -> Updater<Model, C
                         quitting: true,
                         ..*model
                     })),
-                    Some(|| Event::Quit),
+                    Some(Cmd::quit()),
                 );
             }
             (
@@ -132,7 +148,7 @@ fn update_choices(event: &Event<CustomEvent>, model: &Model) -> Updater<Model, C
                     ticks: model.ticks - 1,
                     ..*model
                 })),
-                Some(tick),
+                Some(tick()),
             )
         }
         _ => (None, None),
@@ -157,7 +173,7 @@ fn update_chosen(event: &Event<CustomEvent>, model: &Model) -> Updater<Model, Cu
                             frames,
                             ..*model
                         })),
-                        Some(tick),
+                        Some(tick()),
                     );
                 }
                 return (
@@ -166,7 +182,7 @@ fn update_chosen(event: &Event<CustomEvent>, model: &Model) -> Updater<Model, Cu
                         progress,
                         ..*model
                     })),
-                    Some(frame),
+                    Some(frame()),
                 );
             }
             (Some(Box::new(Model { ..*model })), None)
@@ -179,7 +195,7 @@ fn update_chosen(event: &Event<CustomEvent>, model: &Model) -> Updater<Model, Cu
                             quitting: true,
                             ..*model
                         })),
-                        Some(|| Event::Quit),
+                        Some(Cmd::quit()),
                     );
                 }
                 return (
@@ -187,7 +203,7 @@ fn update_chosen(event: &Event<CustomEvent>, model: &Model) -> Updater<Model, Cu
                         ticks: model.ticks - 1,
                         ..*model
                     })),
-                    Some(tick),
+                    Some(tick()),
                 );
             }
             (Some(Box::new(Model { ..*model })), None)
@@ -197,37 +213,36 @@ fn update_chosen(event: &Event<CustomEvent>, model: &Model) -> Updater<Model, Cu
                 quitting: true,
                 ..*model
             })),
-            Some(|| Event::Quit),
+            Some(Cmd::quit()),
         ),
         _ => (None, None),
     }
 }
 
-fn tick() -> Event<CustomEvent> {
-    let one_sec = Duration::from_secs(1);
-    std::thread::sleep(one_sec);
-    Event::Custom(CustomEvent::Tick)
+fn tick() -> Cmd<CustomEvent> {
+    Cmd::tick(Duration::from_secs(1), |_| Event::Custom(CustomEvent::Tick))
 }
 
-fn frame() -> Event<CustomEvent> {
-    let one_frame = Duration::from_secs_f64(1f64 / 60f64);
-    std::thread::sleep(one_frame);
-    Event::Custom(CustomEvent::Frame)
+fn frame() -> Cmd<CustomEvent> {
+    Cmd::tick(Duration::from_secs_f64(1f64 / 60f64), |_| {
+        Event::Custom(CustomEvent::Frame)
+    })
 }
 
-fn main() {
-    Program::new(Box::new(Model::new())).run();
+fn main() -> Result<(), TermixError> {
+    Program::new(Box::new(Model::new())).run()?;
+    Ok(())
 }
 
 fn outbounce(t: f64) -> f64 {
     if t < (4f64 / 11.0) {
         (121f64 * t * t) / 16.0
     } else if t < (8f64 / 11.0) {
-        return (363f64 / 40.0 * t * t) - (99f64 / 10.0 * t) + (17f64 / 5.0);
+        (363f64 / 40.0 * t * t) - (99f64 / 10.0 * t) + (17f64 / 5.0)
     } else if t < (9f64 / 10.0) {
-        return (4356f64 / 361.0 * t * t) - (35442f64 / 1805.0 * t) + (16061f64 / 1805.0);
+        (4356f64 / 361.0 * t * t) - (35442f64 / 1805.0 * t) + (16061f64 / 1805.0)
     } else {
-        return (54f64 / 5.0 * t * t) - (513f64 / 25.0 * t) + (268f64 / 25.0);
+        (54f64 / 5.0 * t * t) - (513f64 / 25.0 * t) + (268f64 / 25.0)
     }
 }
 fn checkbox(label: &str, checked: bool) -> String {
@@ -289,15 +304,17 @@ fn choices_view(model: &Model) -> String {
 
     tpl
 }
-fn progressbar(percent: f64) -> String {
-    let w = PROGRESS_BAR_WIDTH as f64;
+fn progressbar(percent: f64, cols: u16) -> String {
+    let w = (cols as usize)
+        .saturating_sub(PROGRESS_BAR_PADDING)
+        .clamp(1, MAX_PROGRESS_BAR_WIDTH) as f64;
     let full_size = (w * percent).round() as usize;
     let mut full_cells = String::new();
     let ramp_res = ramp();
     (0..full_size).for_each(|i| {
         full_cells += &StyledText::new(
             PROGRESS_FULL_CHAR,
-            Some(ramp_res[i].clone()),
+            Some(ramp_res[i * MAX_PROGRESS_BAR_WIDTH / w as usize].clone()),
             None,
             None,
             None,
@@ -358,5 +375,5 @@ fn chosen_view(m: &Model) -> String {
         String::from("Downloading...")
     };
 
-    msg + "\n\n" + &label + "\n" + &progressbar(m.progress) + "%"
+    msg + "\n\n" + &label + "\n" + &progressbar(m.progress, m.cols) + "%"
 }
@@ -4,8 +4,9 @@ use std::collections::HashMap;
 use std::str::FromStr;
 
 // ref: https://man7.org/linux/man-pages/man4/console_codes.4.html
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum Color {
+    #[default]
     Inherit,
     Ansi16(Ansi16Value),
     Ansi256(u32),
@@ -60,12 +61,6 @@ pub enum Ansi16Value {
 
 impl Color {}
 
-impl Default for Color {
-    fn default() -> Self {
-        Color::Inherit
-    }
-}
-
 impl Default for StyledText {
     fn default() -> Self {
         StyledText {
@@ -0,0 +1,109 @@
+//! Child models embedded in a parent model.
+//!
+//! A widget is a `Model` with its own custom event type, so it can be
+//! published separately. The parent holds it as a `Component`, which routes
+//! the parent's events to the widget and maps the widget's commands back
+//! into the parent's event type.
+
+use std::{fmt::Debug, sync::Arc};
+
+use crate::{
+    event::Event,
+    model::{Cmd, Model},
+};
+
+type Lift<Child, Parent> = Arc<dyn Fn(Child) -> Parent + Send + Sync + 'static>;
+type Lower<Parent, Child> = Box<dyn Fn(&Parent) -> Option<Child> + Send + Sync + 'static>;
+
+/// Child model with the functions converting its custom events from and to the parent's.
+///
+/// ```
+/// use termix::{component::Component, event::Event, model::{Cmd, Model}};
+///
+/// struct Counter(u32);
+///
+/// impl Model<()> for Counter {
+///     fn update(&mut self, _: &Event<()>) -> Option<Cmd<()>> {
+///         self.0 += 1;
+///         None
+///     }
+///     fn view(&self) -> String {
+///         self.0.to_string()
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// enum Msg {
+///     Left(()),
+///     Right(()),
+/// }
+///
+/// let mut left = Component::new(Counter(0), Msg::Left, |msg| match msg {
+///     Msg::Left(m) => Some(*m),
+///     _ => None,
+/// });
+/// left.update(&Event::Custom(Msg::Left(())));
+/// left.update(&Event::Custom(Msg::Right(())));
+/// assert_eq!(left.view(), "1");
+/// ```
+pub struct Component<M, Child, Parent>
+where
+    Child: Send + Debug,
+{
+    model: M,
+    lift: Lift<Child, Parent>,
+    lower: Lower<Parent, Child>,
+}
+
+impl<M, Child, Parent> Component<M, Child, Parent>
+where
+    M: Model<Child>,
+    Child: Send + Debug + 'static,
+    Parent: Send + Debug + 'static,
+{
+    /// `lift` wraps events of the child into the parent's event type,
+    /// and `lower` picks the events addressed to the child.
+    pub fn new<L, W>(model: M, lift: L, lower: W) -> Component<M, Child, Parent>
+    where
+        L: Fn(Child) -> Parent + Send + Sync + 'static,
+        W: Fn(&Parent) -> Option<Child> + Send + Sync + 'static,
+    {
+        Component {
+            model,
+            lift: Arc::new(lift),
+            lower: Box::new(lower),
+        }
+    }
+
+    /// Forwards the event to the child, unless it is a custom event for someone else.
+    pub fn update(&mut self, event: &Event<Parent>) -> Option<Cmd<Parent>> {
+        let event = event.lower(&self.lower)?;
+        self.model.update(&event).map(|cmd| self.lift(cmd))
+    }
+
+    pub fn view(&self) -> String {
+        self.model.view()
+    }
+
+    pub fn init(&self) -> Option<Cmd<Parent>> {
+        self.model.init().map(|cmd| self.lift(cmd))
+    }
+
+    pub fn changed(&self) -> bool {
+        self.model.changed()
+    }
+
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+
+    pub fn model_mut(&mut self) -> &mut M {
+        &mut self.model
+    }
+
+    /// Maps the command of the child into the parent's event type.
+    pub fn lift(&self, cmd: Cmd<Child>) -> Cmd<Parent> {
+        let lift = self.lift.clone();
+        cmd.map(move |e| lift(e))
+    }
+}
@@ -0,0 +1,147 @@
+//! Handling of the messages of the event loop, shared by `Program` and `TestProgram`.
+
+use std::{
+    fmt::Debug,
+    io,
+    process::{Command, ExitStatus},
+};
+
+use nix::sys::signal::Signal;
+
+use crate::{
+    error::TermixError,
+    event::{Event, Message},
+    key::Key,
+    model::{Cmd, Control, Model},
+    options::ProgramOptions,
+};
+
+/// Filter typed with the model and the custom event of the program.
+pub(crate) type Filter<M, E> = Box<dyn Fn(&M, Event<E>) -> Option<Event<E>> + Send>;
+
+/// Side effects of the event loop, applied to the terminal by `Program`
+/// and recorded by `TestProgram`.
+pub(crate) trait Host<E: Send + Debug> {
+    /// Hands the terminal back to the shell until the process is continued.
+    fn suspend(&mut self) -> Result<(), TermixError>;
+    /// Takes the terminal back after the process was stopped by someone else.
+    fn resume(&mut self) -> Result<(), TermixError>;
+    fn control(&mut self, control: Control) -> Result<(), TermixError>;
+    /// Runs the process of `Cmd::exec` until it exits.
+    fn exec(&mut self, command: Command) -> Result<io::Result<ExitStatus>, TermixError>;
+    fn resize(&mut self, cols: u16);
+    fn render(&mut self, view: String) -> Result<(), TermixError>;
+    /// Starts the command without blocking the event loop.
+    fn spawn(&mut self, cmd: Cmd<E>);
+}
+
+/// Whether the event loop goes on after a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Flow {
+    Continue,
+    Quit,
+}
+
+/// Model of a program and the state the event loop keeps between messages.
+pub(crate) struct Dispatcher<M, E: Send + Debug> {
+    pub(crate) model: M,
+    pub(crate) filters: Vec<Filter<M, E>>,
+    suspend_on_ctrl_z: bool,
+    handle_interrupt: bool,
+    // stopped by the program itself, so the next SIGCONT is expected
+    suspended: bool,
+    // signal which stopped the program
+    pub(crate) signal: Option<Signal>,
+}
+
+impl<M: Model<E>, E: Send + Debug> Dispatcher<M, E> {
+    pub(crate) fn new(model: M, options: &ProgramOptions) -> Dispatcher<M, E> {
+        Dispatcher {
+            model,
+            filters: vec![],
+            suspend_on_ctrl_z: options.suspend_on_ctrl_z,
+            handle_interrupt: options.handle_interrupt,
+            suspended: false,
+            signal: None,
+        }
+    }
+
+    /// Starts the init command and renders the first view.
+    pub(crate) fn start(&mut self, host: &mut impl Host<E>) -> Result<(), TermixError> {
+        if let Some(cmd) = self.model.init() {
+            host.spawn(cmd);
+        }
+        host.render(self.model.view())
+    }
+
+    /// Handles a message of the event loop. Returns `Flow::Quit` once the program quits.
+    pub(crate) fn dispatch(
+        &mut self,
+        msg: Message<E>,
+        host: &mut impl Host<E>,
+    ) -> Result<Flow, TermixError> {
+        let ev = match msg {
+            Message::Event(Event::Keyboard(Key::Ctrl('z'))) if self.suspend_on_ctrl_z => {
+                self.suspend(host)?
+            }
+            Message::Scoped(scope, _) if scope.is_cancelled() => return Ok(Flow::Continue),
+            Message::Event(ev) | Message::Scoped(_, ev) => {
+                if let Event::Resize { cols, .. } = ev {
+                    host.resize(cols);
+                }
+                ev
+            }
+            Message::Control(Control::Suspend) | Message::Signal(Signal::SIGTSTP) => {
+                self.suspend(host)?
+            }
+            Message::Control(control) => {
+                host.control(control)?;
+                return Ok(Flow::Continue);
+            }
+            Message::Exec(command, callback) => match callback(host.exec(command)?) {
+                Some(ev) => ev,
+                None => return Ok(Flow::Continue),
+            },
+            // continued after `suspend`, which has already taken the terminal back
+            Message::Signal(Signal::SIGCONT) if self.suspended => {
+                self.suspended = false;
+                return Ok(Flow::Continue);
+            }
+            // continued after being stopped by someone else, e.g. SIGSTOP
+            Message::Signal(Signal::SIGCONT) => {
+                host.resume()?;
+                Event::Resume
+            }
+            Message::Signal(Signal::SIGINT | Signal::SIGTERM) if self.handle_interrupt => {
+                Event::Interrupt
+            }
+            Message::Signal(sig) => {
+                self.signal = Some(sig);
+                return Err(TermixError::Interrupted);
+            }
+            Message::Kill => return Err(TermixError::Killed),
+            Message::Error(err) => return Err(err),
+        };
+        let model = &self.model;
+        let Some(ev) = self.filters.iter().try_fold(ev, |ev, f| f(model, ev)) else {
+            return Ok(Flow::Continue);
+        };
+        if let Event::Quit = ev {
+            return Ok(Flow::Quit);
+        }
+        let cmd = self.model.update(&ev);
+        if self.model.changed() {
+            host.render(self.model.view())?;
+        }
+        if let Some(cmd) = cmd {
+            host.spawn(cmd);
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn suspend(&mut self, host: &mut impl Host<E>) -> Result<Event<E>, TermixError> {
+        host.suspend()?;
+        self.suspended = true;
+        Ok(Event::Resume)
+    }
+}
@@ -2,12 +2,26 @@
 pub enum TermixError {
     #[error("Failed to write to stdout")]
     Write(String),
-    #[error("Failed to detect cursor position")]
-    CursorDetection,
     #[error("Failed to listen keys")]
     KeyListener,
     #[error("Unexpected byte")]
     KeyRead(u8),
+    #[error("Program was interrupted by a signal")]
+    Interrupted,
+    #[error("Program was killed")]
+    Killed,
+    #[error("Command panicked: {0}")]
+    Panic(String),
+    #[error("Tokio runtime can't run the program: {0}")]
+    Runtime(String),
+    #[error("Custom input or output is already used by another program")]
+    StreamInUse,
+    #[error("Failed to open tty")]
+    Tty(#[source] std::io::Error),
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("System call failed")]
+    Sys(#[from] nix::Error),
     #[error("Something happend")]
     Any(#[from] anyhow::Error),
 }
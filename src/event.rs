@@ -1,8 +1,24 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    io,
+    process::{Command, ExitStatus},
+};
 
-use crate::key::Key;
+use nix::sys::signal::Signal;
+
+use crate::{
+    error::TermixError,
+    key::Key,
+    model::{Control, Scope},
+};
 
 /// Init event and quit event is already defined.
+/// Resize event is fired right after init event and whenever the terminal is resized.
+/// Interrupt event is fired on SIGINT or SIGTERM only if `ProgramOptions::handle_interrupt`
+/// is enabled. The model can quit, or ignore it to keep running.
+/// Resume event is fired when the program comes back after being suspended.
+/// Panic event is fired with the panic message when a command panics
+/// and `ProgramOptions::catch_panics` is enabled.
 #[derive(Clone, Debug)]
 pub enum Event<CustomEvent>
 where
@@ -11,5 +27,87 @@ where
     Init,
     Quit,
     Keyboard(Key),
+    Resize { cols: u16, rows: u16 },
+    Interrupt,
+    Resume,
+    Panic(String),
     Custom(CustomEvent),
 }
+
+impl<CustomEvent> Event<CustomEvent>
+where
+    CustomEvent: Send + Debug,
+{
+    /// Converts the custom event, keeping the other events as they are.
+    pub fn map<Parent, F>(self, f: F) -> Event<Parent>
+    where
+        Parent: Send + Debug,
+        F: FnOnce(CustomEvent) -> Parent,
+    {
+        match self {
+            Event::Custom(e) => Event::Custom(f(e)),
+            ev => ev.lower(|_| None).expect("not a custom event"),
+        }
+    }
+
+    /// Converts the event for a child component.
+    /// Returns `None` if the custom event is not addressed to the child.
+    pub fn lower<Child, F>(&self, f: F) -> Option<Event<Child>>
+    where
+        Child: Send + Debug,
+        F: FnOnce(&CustomEvent) -> Option<Child>,
+    {
+        let ev = match self {
+            Event::Init => Event::Init,
+            Event::Quit => Event::Quit,
+            Event::Keyboard(key) => Event::Keyboard(key.clone()),
+            Event::Resize { cols, rows } => Event::Resize {
+                cols: *cols,
+                rows: *rows,
+            },
+            Event::Interrupt => Event::Interrupt,
+            Event::Resume => Event::Resume,
+            Event::Panic(msg) => Event::Panic(msg.clone()),
+            Event::Custom(e) => Event::Custom(f(e)?),
+        };
+        Some(ev)
+    }
+}
+
+/// Called with the exit status of `Message::Exec`. Returns `None` if the command was cancelled.
+pub(crate) type ExecHandler<CustomEvent> =
+    Box<dyn FnOnce(io::Result<ExitStatus>) -> Option<Event<CustomEvent>> + Send + 'static>;
+
+/// Message passed to the event loop of `Program`.
+pub(crate) enum Message<CustomEvent>
+where
+    CustomEvent: Send + Debug,
+{
+    Event(Event<CustomEvent>),
+    // event of a cancellable command, dropped if it has been cancelled
+    Scoped(Scope, Event<CustomEvent>),
+    Control(Control),
+    Exec(Command, ExecHandler<CustomEvent>),
+    Signal(Signal),
+    Kill,
+    Error(TermixError),
+}
+
+/// Sends messages to the event loop.
+/// With `tokio` feature, the event loop awaits them on the runtime.
+#[cfg(not(feature = "tokio"))]
+pub(crate) type MessageSender<E> = std::sync::mpsc::Sender<Message<E>>;
+#[cfg(feature = "tokio")]
+pub(crate) type MessageSender<E> = tokio::sync::mpsc::UnboundedSender<Message<E>>;
+
+#[cfg(not(feature = "tokio"))]
+pub(crate) type MessageReceiver<E> = std::sync::mpsc::Receiver<Message<E>>;
+#[cfg(feature = "tokio")]
+pub(crate) type MessageReceiver<E> = tokio::sync::mpsc::UnboundedReceiver<Message<E>>;
+
+pub(crate) fn message_channel<E: Send + Debug>() -> (MessageSender<E>, MessageReceiver<E>) {
+    #[cfg(not(feature = "tokio"))]
+    return std::sync::mpsc::channel();
+    #[cfg(feature = "tokio")]
+    return tokio::sync::mpsc::unbounded_channel();
+}
@@ -0,0 +1,660 @@
+use std::{fmt::Debug, process::Command, sync::Arc, time::Instant};
+#[cfg(feature = "tokio")]
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::mpsc::channel,
+    task::{Context, Poll},
+    time::Duration,
+};
+#[cfg(not(feature = "tokio"))]
+use std::{sync::Mutex, vec};
+
+#[cfg(feature = "tokio")]
+use tokio::{
+    runtime::{Builder, Handle, Runtime, RuntimeFlavor},
+    signal::unix::{signal, SignalKind},
+    sync::Semaphore,
+};
+
+use crate::{
+    error::TermixError,
+    event::{Event, Message, MessageSender},
+    model::{CancellationToken, Cmd, CmdKind, ExecCallback, Scope},
+    options::ProgramOptions,
+    panic,
+    timer::next_boundary,
+};
+#[cfg(not(feature = "tokio"))]
+use crate::{pool::Pool, timer::Timer};
+
+/// Called when a command has sent all of its events.
+type Done = Box<dyn FnOnce() + Send + 'static>;
+
+/// Runs commands of a program and sends their events to the event loop.
+#[cfg(not(feature = "tokio"))]
+pub struct Executor<E: Send + Debug> {
+    tx: MessageSender<E>,
+    pool: Pool,
+    timer: Timer,
+    catch_panics: bool,
+    // cancelled on shutdown, so that pending commands are skipped
+    root: CancellationToken,
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<E: Send + Debug> Clone for Executor<E> {
+    fn clone(&self) -> Self {
+        Executor {
+            tx: self.tx.clone(),
+            pool: self.pool.clone(),
+            timer: self.timer.clone(),
+            catch_panics: self.catch_panics,
+            root: self.root.clone(),
+        }
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<E: Send + Debug + 'static> Executor<E> {
+    pub fn new(tx: MessageSender<E>) -> Executor<E> {
+        Executor {
+            tx,
+            pool: Pool::new(ProgramOptions::DEFAULT_WORKERS),
+            timer: Timer::new(),
+            catch_panics: false,
+            root: CancellationToken::new(),
+        }
+    }
+
+    /// Runs at most `workers` commands at once.
+    pub fn workers(mut self, workers: usize) -> Executor<E> {
+        self.pool = Pool::new(workers);
+        self
+    }
+
+    /// Starts the command without blocking the event loop.
+    pub fn spawn(&self, cmd: Cmd<E>) {
+        self.start(cmd, self.scope(), Box::new(|| {}));
+    }
+
+    /// Skips the commands which have not started, and stops the idle workers and the
+    /// timer thread. Running commands are not interrupted, and their events are dropped.
+    pub fn shutdown(&self) {
+        self.root.cancel();
+        self.pool.shutdown();
+        self.timer.stop();
+    }
+
+    /// Starts the command and calls `done` after all of its events have been sent.
+    /// Nothing blocks on the workers, so nested commands can't exhaust the pool.
+    fn start(&self, cmd: Cmd<E>, scope: Scope, done: Done) {
+        if scope.is_cancelled() {
+            return done();
+        }
+        match cmd.kind {
+            CmdKind::Func(f) => {
+                let executor = self.clone();
+                self.pool.execute(move || {
+                    if !scope.is_cancelled() {
+                        executor.send_event(&scope, f);
+                    }
+                    done();
+                });
+            }
+            CmdKind::Batch(cmds) => self.batch(cmds, scope, done),
+            CmdKind::Sequence(cmds) => self.sequence(cmds.into_iter(), scope, done),
+            CmdKind::Control(control) => {
+                let _ = self.tx.send(Message::Control(control));
+                done();
+            }
+            CmdKind::Exec(command, callback) => {
+                let _ = self.tx.send(exec(command, callback, scope, done));
+            }
+            CmdKind::Tick {
+                duration,
+                aligned,
+                f,
+            } => {
+                let executor = self.clone();
+                self.timer.schedule(deadline(duration, aligned), move |at| {
+                    if !scope.is_cancelled() {
+                        executor.send_event(&scope, || f(at));
+                    }
+                    done();
+                });
+            }
+            CmdKind::Cancellable(token, cmd) => self.start(*cmd, scope.with(token), done),
+        }
+    }
+
+    fn batch(&self, cmds: Vec<Cmd<E>>, scope: Scope, done: Done) {
+        if cmds.is_empty() {
+            return done();
+        }
+        // the last command to finish calls `done`
+        let left = Arc::new(Mutex::new((cmds.len(), Some(done))));
+        for cmd in cmds {
+            let left = left.clone();
+            self.start(
+                cmd,
+                scope.clone(),
+                Box::new(move || {
+                    let mut left = left.lock().unwrap_or_else(|e| e.into_inner());
+                    left.0 -= 1;
+                    if left.0 > 0 {
+                        return;
+                    }
+                    if let Some(done) = left.1.take() {
+                        drop(left);
+                        done();
+                    }
+                }),
+            );
+        }
+    }
+
+    fn sequence(&self, mut cmds: vec::IntoIter<Cmd<E>>, scope: Scope, done: Done) {
+        match cmds.next() {
+            Some(cmd) => {
+                let executor = self.clone();
+                let next = scope.clone();
+                self.start(
+                    cmd,
+                    scope,
+                    Box::new(move || executor.sequence(cmds, next, done)),
+                );
+            }
+            None => done(),
+        }
+    }
+}
+
+/// Runs commands of a program on a tokio runtime and sends their events to the event loop.
+#[cfg(feature = "tokio")]
+pub struct Executor<E: Send + Debug> {
+    tx: MessageSender<E>,
+    handle: Handle,
+    // limits the blocking commands running at once
+    workers: Arc<Semaphore>,
+    catch_panics: bool,
+    // cancelled on shutdown, so that pending commands are skipped
+    root: CancellationToken,
+}
+
+/// Runtime a program runs on: the one given by `ProgramOptions::runtime`, the one
+/// the program is started in, or one owned by the program when there is none.
+#[cfg(feature = "tokio")]
+pub struct ProgramRuntime {
+    handle: Handle,
+    owned: Option<Runtime>,
+}
+
+#[cfg(feature = "tokio")]
+impl ProgramRuntime {
+    pub fn new(handle: Option<Handle>) -> Result<ProgramRuntime, TermixError> {
+        let runtime = match handle.or_else(|| Handle::try_current().ok()) {
+            Some(handle) => ProgramRuntime {
+                handle,
+                owned: None,
+            },
+            None => {
+                let runtime = Builder::new_multi_thread()
+                    .enable_io()
+                    .enable_time()
+                    .build()
+                    .map_err(|e| TermixError::Runtime(e.to_string()))?;
+                ProgramRuntime {
+                    handle: runtime.handle().clone(),
+                    owned: Some(runtime),
+                }
+            }
+        };
+        check_runtime(&runtime.handle)?;
+        Ok(runtime)
+    }
+
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for ProgramRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.owned.take() {
+            // blocking commands may still be running; don't wait for them
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// Checks that the program can run on the runtime.
+#[cfg(feature = "tokio")]
+pub fn check_runtime(handle: &Handle) -> Result<(), TermixError> {
+    // the event loop blocks the thread it runs on while a child process runs
+    if handle.runtime_flavor() == RuntimeFlavor::CurrentThread {
+        return Err(TermixError::Runtime(String::from(
+            "current_thread runtime is not supported",
+        )));
+    }
+    // the drivers panic when they are disabled
+    let _guard = handle.enter();
+    panic::catch(true, || drop(tokio::time::sleep(Duration::ZERO)))
+        .map_err(|_| TermixError::Runtime(String::from("time driver is not enabled")))?;
+    // keys are read and resizes are watched by the I/O driver
+    panic::catch(true, || signal(SignalKind::window_change()).map(drop))
+        .map_err(|_| TermixError::Runtime(String::from("I/O driver is not enabled")))?
+        .map_err(TermixError::from)
+}
+
+#[cfg(feature = "tokio")]
+impl<E: Send + Debug> Clone for Executor<E> {
+    fn clone(&self) -> Self {
+        Executor {
+            tx: self.tx.clone(),
+            handle: self.handle.clone(),
+            workers: self.workers.clone(),
+            catch_panics: self.catch_panics,
+            root: self.root.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+#[cfg(feature = "tokio")]
+impl<E: Send + Debug + 'static> Executor<E> {
+    pub fn with_runtime(tx: MessageSender<E>, handle: Handle) -> Executor<E> {
+        Executor {
+            tx,
+            handle,
+            workers: Arc::new(Semaphore::new(ProgramOptions::DEFAULT_WORKERS)),
+            catch_panics: false,
+            root: CancellationToken::new(),
+        }
+    }
+
+    /// Runtime on which the commands, and the sources of events of the program, run.
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Runs at most `workers` blocking commands at once.
+    pub fn workers(mut self, workers: usize) -> Executor<E> {
+        self.workers = Arc::new(Semaphore::new(workers.max(1)));
+        self
+    }
+
+    /// Starts the command without blocking the event loop.
+    pub fn spawn(&self, cmd: Cmd<E>) {
+        match cmd.kind {
+            CmdKind::Control(control) => {
+                let _ = self.tx.send(Message::Control(control));
+            }
+            CmdKind::Exec(command, callback) => {
+                let _ = self
+                    .tx
+                    .send(exec(command, callback, self.scope(), Box::new(|| {})));
+            }
+            kind => {
+                self.handle
+                    .spawn(self.clone().run(Cmd { kind }, self.scope()));
+            }
+        }
+    }
+
+    /// Skips the commands which have not started. Running commands are not
+    /// interrupted, and their events are dropped.
+    pub fn shutdown(&self) {
+        self.root.cancel();
+    }
+
+    /// Runs the command and resolves after all of its events have been sent.
+    fn run(self, cmd: Cmd<E>, scope: Scope) -> Task {
+        Box::pin(async move {
+            if scope.is_cancelled() {
+                return;
+            }
+            match cmd.kind {
+                CmdKind::Func(f) => {
+                    let Ok(_permit) = self.workers.clone().acquire_owned().await else {
+                        return;
+                    };
+                    let executor = self.clone();
+                    let _ = tokio::task::spawn_blocking(move || {
+                        if !scope.is_cancelled() {
+                            executor.send_event(&scope, f);
+                        }
+                    })
+                    .await;
+                }
+                CmdKind::Future(future) => {
+                    let silent = self.catch_panics;
+                    let result = CatchUnwind { future, silent }.await;
+                    let _ = self.tx.send(self.caught(&scope, result));
+                }
+                CmdKind::Batch(cmds) => {
+                    let handles: Vec<_> = cmds
+                        .into_iter()
+                        .map(|cmd| self.handle.spawn(self.clone().run(cmd, scope.clone())))
+                        .collect();
+                    for h in handles {
+                        let _ = h.await;
+                    }
+                }
+                CmdKind::Sequence(cmds) => {
+                    for cmd in cmds {
+                        self.clone().run(cmd, scope.clone()).await;
+                    }
+                }
+                CmdKind::Control(control) => {
+                    let _ = self.tx.send(Message::Control(control));
+                }
+                CmdKind::Exec(command, callback) => {
+                    let (done_tx, done_rx) = channel();
+                    let done = Box::new(move || {
+                        let _ = done_tx.send(());
+                    });
+                    let _ = self.tx.send(exec(command, callback, scope, done));
+                    let _ = tokio::task::spawn_blocking(move || done_rx.recv()).await;
+                }
+                CmdKind::Tick {
+                    duration,
+                    aligned,
+                    f,
+                } => {
+                    let at = deadline(duration, aligned);
+                    tokio::time::sleep_until(at.into()).await;
+                    if !scope.is_cancelled() {
+                        self.send_event(&scope, || f(Instant::now()));
+                    }
+                }
+                CmdKind::Cancellable(token, cmd) => self.run(*cmd, scope.with(token)).await,
+            }
+        })
+    }
+}
+
+impl<E: Send + Debug + 'static> Executor<E> {
+    fn scope(&self) -> Scope {
+        Scope::default().with(self.root.clone())
+    }
+
+    /// Delivers panics of commands as `Event::Panic` instead of `TermixError::Panic`.
+    pub fn catch_panics(mut self, enabled: bool) -> Executor<E> {
+        self.catch_panics = enabled;
+        self
+    }
+
+    /// Sends the event made by `f`, or its panic.
+    fn send_event(&self, scope: &Scope, f: impl FnOnce() -> Event<E>) {
+        let result = panic::catch(self.catch_panics, f);
+        let _ = self.tx.send(self.caught(scope, result));
+    }
+
+    fn caught(&self, scope: &Scope, result: Result<Event<E>, String>) -> Message<E> {
+        let ev = match result {
+            Ok(ev) => ev,
+            Err(msg) if self.catch_panics => Event::Panic(msg),
+            Err(msg) => return Message::Error(TermixError::Panic(msg)),
+        };
+        // checked again by the event loop, since it may be cancelled while queued
+        Message::Scoped(scope.clone(), ev)
+    }
+}
+
+/// Makes the message to run the process in the event loop.
+/// `done` is called after the process exits.
+pub(crate) fn exec<E: Send + Debug + 'static>(
+    command: Command,
+    callback: ExecCallback<E>,
+    scope: Scope,
+    done: Done,
+) -> Message<E> {
+    Message::Exec(
+        command,
+        Box::new(move |status| {
+            let ev = (!scope.is_cancelled()).then(|| callback(status));
+            done();
+            ev
+        }),
+    )
+}
+
+/// Resolves to the panic message if polling the future panics.
+#[cfg(feature = "tokio")]
+struct CatchUnwind<F> {
+    future: F,
+    silent: bool,
+}
+
+#[cfg(feature = "tokio")]
+impl<F: Future + Unpin> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, String>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match panic::catch(this.silent, || Pin::new(&mut this.future).poll(cx)) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(msg) => Poll::Ready(Err(msg)),
+        }
+    }
+}
+
+fn deadline(duration: std::time::Duration, aligned: bool) -> Instant {
+    if aligned {
+        next_boundary(duration)
+    } else {
+        Instant::now() + duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc::{channel, Sender},
+        time::{Duration, Instant},
+    };
+
+    use super::Executor;
+    use crate::{
+        error::TermixError,
+        event::{message_channel, Event, Message, MessageReceiver},
+        model::{CancellationToken, Cmd},
+    };
+
+    fn executor() -> (Executor<u64>, Events) {
+        let (tx, rx) = message_channel();
+        #[cfg(not(feature = "tokio"))]
+        let executor = Executor::new(tx);
+        #[cfg(feature = "tokio")]
+        let executor = Executor::with_runtime(tx, runtime().clone());
+        (executor, Events(rx))
+    }
+
+    #[cfg(feature = "tokio")]
+    fn runtime() -> &'static tokio::runtime::Handle {
+        static RUNTIME: std::sync::OnceLock<tokio::runtime::Runtime> = std::sync::OnceLock::new();
+        RUNTIME
+            .get_or_init(|| {
+                tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap()
+            })
+            .handle()
+    }
+
+    /// Messages sent by the executor, received by the test thread.
+    struct Events(MessageReceiver<u64>);
+
+    impl Events {
+        fn recv(&mut self) -> Message<u64> {
+            #[cfg(not(feature = "tokio"))]
+            return self.0.recv().unwrap();
+            #[cfg(feature = "tokio")]
+            return self.0.blocking_recv().unwrap();
+        }
+
+        fn customs(&mut self, n: usize) -> Vec<u64> {
+            (0..n).map(|_| custom(self.recv())).collect()
+        }
+    }
+
+    fn custom_cmd(n: u64) -> Cmd<u64> {
+        Cmd::new(move || Event::Custom(n))
+    }
+
+    /// Command firing `n` once the returned gate is opened.
+    fn gated(n: u64) -> (Sender<()>, Cmd<u64>) {
+        let (gate_tx, gate_rx) = channel::<()>();
+        let cmd = Cmd::new(move || {
+            let _ = gate_rx.recv();
+            Event::Custom(n)
+        });
+        (gate_tx, cmd)
+    }
+
+    fn custom(msg: Message<u64>) -> u64 {
+        match msg {
+            Message::Scoped(_, Event::Custom(n)) => n,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn batch_delivers_as_finished() {
+        let (executor, mut rx) = executor();
+        let (gates, cmds): (Vec<_>, Vec<_>) = (1..=3).map(gated).unzip();
+        executor.spawn(Cmd::batch(cmds));
+        for n in [2, 3, 1] {
+            gates[n as usize - 1].send(()).unwrap();
+            assert_eq!(custom(rx.recv()), n);
+        }
+    }
+
+    #[test]
+    fn sequence_keeps_order() {
+        let (executor, mut rx) = executor();
+        let (first, slow) = gated(3);
+        let (second, batched) = gated(2);
+        executor.spawn(Cmd::sequence(vec![
+            slow,
+            Cmd::batch(vec![batched, custom_cmd(1)]),
+            Cmd::tick(Duration::from_millis(10), |_| Event::Custom(4)),
+            custom_cmd(0),
+        ]));
+        first.send(()).unwrap();
+        assert_eq!(custom(rx.recv()), 3);
+        assert_eq!(custom(rx.recv()), 1);
+        second.send(()).unwrap();
+        let got: Vec<u64> = rx.customs(3);
+        assert_eq!(got, vec![2, 4, 0]);
+    }
+
+    #[test]
+    fn one_worker_runs_nested_commands() {
+        let (executor, mut rx) = executor();
+        let executor = executor.workers(1);
+        executor.spawn(Cmd::sequence(vec![
+            Cmd::batch(vec![custom_cmd(2), custom_cmd(1)]),
+            custom_cmd(0),
+        ]));
+        let mut got: Vec<u64> = rx.customs(3);
+        assert_eq!(got.pop(), Some(0));
+        got.sort();
+        assert_eq!(got, vec![1, 2]);
+    }
+
+    #[test]
+    fn cancelled_commands_are_dropped() {
+        let (executor, mut rx) = executor();
+        // cancelled before it starts: never runs
+        let token = CancellationToken::new();
+        token.cancel();
+        executor.spawn(Cmd::new(|| unreachable!()).with_token(&token));
+        // cancelled while running: the late event is marked as cancelled
+        let token = CancellationToken::new();
+        let (started_tx, started_rx) = channel();
+        let (gate_tx, gate_rx) = channel::<()>();
+        executor.spawn(
+            Cmd::new(move || {
+                let _ = started_tx.send(());
+                let _ = gate_rx.recv();
+                Event::Custom(1)
+            })
+            .with_token(&token),
+        );
+        started_rx.recv().unwrap();
+        token.cancel();
+        gate_tx.send(()).unwrap();
+        match rx.recv() {
+            Message::Scoped(scope, Event::Custom(1)) => assert!(scope.is_cancelled()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn tick_fires_after_duration() {
+        let (executor, mut rx) = executor();
+        let start = Instant::now();
+        // each tick fires the milliseconds passed until it fired
+        let tick = |ms| {
+            Cmd::tick(Duration::from_millis(ms), move |at| {
+                Event::Custom(at.duration_since(start).as_millis() as u64)
+            })
+        };
+        executor.spawn(Cmd::batch(vec![tick(20), tick(10)]));
+        let mut got: Vec<u64> = rx.customs(2);
+        got.sort();
+        assert!(got[0] >= 10 && got[1] >= 20, "{got:?}");
+    }
+
+    #[test]
+    fn panic_as_event_or_error() {
+        let (executor, mut rx) = executor();
+        let executor = executor.catch_panics(true);
+        executor.spawn(Cmd::new(|| panic!("boom")));
+        match rx.recv() {
+            Message::Scoped(_, Event::Panic(msg)) => assert_eq!(msg, "boom"),
+            _ => unreachable!(),
+        }
+        let executor = executor.catch_panics(false);
+        executor.spawn(Cmd::tick(Duration::from_millis(1), |_| panic!("boom")));
+        assert!(matches!(rx.recv(), Message::Error(TermixError::Panic(_))));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn future_runs_on_runtime() {
+        let (executor, mut rx) = executor();
+        executor.spawn(Cmd::sequence(vec![
+            Cmd::future(async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Event::Custom(1)
+            }),
+            custom_cmd(2),
+        ]));
+        let got: Vec<u64> = rx.customs(2);
+        assert_eq!(got, vec![1, 2]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn unusable_runtimes_are_rejected() {
+        use tokio::runtime::Builder;
+
+        let check = |runtime: tokio::runtime::Runtime| super::check_runtime(runtime.handle());
+        let current = Builder::new_current_thread().enable_all().build().unwrap();
+        assert!(matches!(check(current), Err(TermixError::Runtime(_))));
+        let timeless = Builder::new_multi_thread().enable_io().build().unwrap();
+        assert!(matches!(check(timeless), Err(TermixError::Runtime(_))));
+        let ioless = Builder::new_multi_thread().enable_time().build().unwrap();
+        assert!(matches!(check(ioless), Err(TermixError::Runtime(_))));
+        let runtime = Builder::new_multi_thread().enable_all().build().unwrap();
+        assert!(check(runtime).is_ok());
+    }
+}
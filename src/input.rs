@@ -1,3 +1,4 @@
+use nix::errno::Errno;
 use nix::fcntl::fcntl;
 use nix::fcntl::FcntlArg;
 use nix::fcntl::OFlag;
@@ -6,12 +7,24 @@ use nix::sys::time::TimeVal;
 use nix::sys::time::TimeValLike;
 use std::os::unix::prelude::FromRawFd;
 use std::os::unix::prelude::RawFd;
+use std::sync::{Arc, Condvar, Mutex};
+#[cfg(feature = "tokio")]
+use std::task::Poll;
 use std::time::Duration;
-use std::{fs::File, io::Read, os::unix::prelude::AsRawFd};
+use std::{
+    fs::File,
+    io::{Read, Write},
+    os::unix::prelude::AsRawFd,
+};
 
 use crate::error::TermixError;
-use crate::key::Key;
+use crate::key::{Key, MouseButton};
+use crate::options::{InputSource, InputStream};
 use crate::raw::get_tty;
+#[cfg(feature = "tokio")]
+use nix::poll::{poll, PollFd, PollFlags};
+#[cfg(feature = "tokio")]
+use tokio::io::{unix::AsyncFd, Interest};
 
 const KEY_WAIT: Duration = Duration::from_millis(10);
 fn duration_to_timeval(duration: Duration) -> TimeVal {
@@ -35,8 +48,13 @@ pub fn wait_until_ready(
     if let Some(fd) = signal_fd {
         fdset.insert(fd)
     }
-    let n = select::select(None, &mut fdset, None, None, &mut timeout_spec)
-        .map_err(|_| TermixError::KeyListener)?;
+    let n = select::select(None, &mut fdset, None, None, &mut timeout_spec).map_err(|e| {
+        if e == Errno::EINTR {
+            TermixError::KeyListener
+        } else {
+            TermixError::Sys(e)
+        }
+    })?;
 
     if n < 1 {
         Err(TermixError::KeyListener) // this error message will be used in input.rs
@@ -47,42 +65,156 @@ pub fn wait_until_ready(
     }
 }
 
+/// Pauses or stops the key listener from another thread, e.g. while a child
+/// process reads the terminal.
+pub struct ListenerControl {
+    state: Mutex<PauseState>,
+    cond: Condvar,
+    // write end of the self-pipe of `KeyBoard`
+    waker: File,
+}
+
+#[derive(Default)]
+struct PauseState {
+    paused: bool,
+    parked: bool,
+    // the listener is asked to exit
+    stopping: bool,
+    // the listener has exited
+    stopped: bool,
+}
+
+impl ListenerControl {
+    /// Returns once the listener has stopped reading the input.
+    pub fn pause(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.paused = true;
+        let _ = (&self.waker).write(&[0]);
+        while !state.parked && !state.stopped {
+            state = self.cond.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    pub fn resume(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.paused = false;
+            // the listener on the runtime waits for the self-pipe instead
+            let _ = (&self.waker).write(&[0]);
+            self.cond.notify_all();
+        }
+    }
+
+    /// Asks the listener to exit, interrupting the wait for input.
+    pub fn stop(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.stopping = true;
+            let _ = (&self.waker).write(&[0]);
+            self.cond.notify_all();
+        }
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.state.lock().map_or(true, |state| state.stopping)
+    }
+
+    // whether the listener should stop waiting for input
+    fn interrupted(&self) -> bool {
+        self.state
+            .lock()
+            .map_or(true, |state| state.paused || state.stopping)
+    }
+}
+
 pub struct KeyBoard {
-    file: Box<File>,
+    // left in blocking mode, since its open file description may be shared
+    // with the output (e.g. a pty or the terminal of the shell)
+    file: Box<dyn InputStream>,
     // bytes will be poped from front, normally the buffer size will be small(< 10 bytes)
     pub byte_buf: Vec<u8>,
     sig_rx: File,
     next_key: Option<Key>,
+    control: Arc<ListenerControl>,
+    // keys are parsed from `byte_buf` only, without reading the file
+    #[cfg(feature = "tokio")]
+    buffered: bool,
+    // more bytes were needed while parsing from `byte_buf` only
+    #[cfg(feature = "tokio")]
+    starved: bool,
 }
-impl Default for KeyBoard {
-    fn default() -> Self {
-        Self::new()
+
+impl Drop for KeyBoard {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.control.state.lock() {
+            state.stopped = true;
+            self.control.cond.notify_all();
+        }
     }
 }
 
 impl KeyBoard {
-    pub fn new() -> KeyBoard {
-        let file = get_tty();
+    pub fn new(source: &InputSource) -> Result<KeyBoard, TermixError> {
+        let file: Box<dyn InputStream> = match source {
+            InputSource::Tty => get_tty()?,
+            InputSource::Stdin => {
+                let fd = nix::unistd::dup(std::io::stdin().as_raw_fd())?;
+                Box::new(unsafe { File::from_raw_fd(fd) })
+            }
+            InputSource::Custom(stream) => stream.take()?,
+        };
         // the self-pipe trick for interrupt `select`
-        let (rx, _tx) = nix::unistd::pipe().expect("failed to set pipe");
+        let (rx, tx) = nix::unistd::pipe()?;
 
         // set the signal pipe to non-blocking mode
-        let flag = fcntl(rx, FcntlArg::F_GETFL).expect("Get fcntl failed");
-        let mut flag = OFlag::from_bits_truncate(flag);
-        flag.insert(OFlag::O_NONBLOCK);
-        let _ = fcntl(rx, FcntlArg::F_SETFL(flag));
-
-        // set file to non-blocking mode
-        let flag = fcntl(file.as_raw_fd(), FcntlArg::F_GETFL).expect("Get fcntl failed");
-        let mut flag = OFlag::from_bits_truncate(flag);
-        flag.insert(OFlag::O_NONBLOCK);
-        let _ = fcntl(file.as_raw_fd(), FcntlArg::F_SETFL(flag));
-        KeyBoard {
+        for fd in [rx, tx] {
+            let flag = fcntl(fd, FcntlArg::F_GETFL)?;
+            let mut flag = OFlag::from_bits_truncate(flag);
+            flag.insert(OFlag::O_NONBLOCK);
+            fcntl(fd, FcntlArg::F_SETFL(flag))?;
+        }
+        Ok(KeyBoard {
             file,
             sig_rx: unsafe { File::from_raw_fd(rx) },
             byte_buf: vec![],
             next_key: None,
+            control: Arc::new(ListenerControl {
+                state: Mutex::new(PauseState::default()),
+                cond: Condvar::new(),
+                waker: unsafe { File::from_raw_fd(tx) },
+            }),
+            #[cfg(feature = "tokio")]
+            buffered: false,
+            #[cfg(feature = "tokio")]
+            starved: false,
+        })
+    }
+
+    pub fn control(&self) -> Arc<ListenerControl> {
+        self.control.clone()
+    }
+
+    pub fn raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+
+    /// Blocks while the listener is paused, e.g. while a child process reads the input.
+    #[cfg(not(feature = "tokio"))]
+    pub fn park_if_paused(&mut self) -> Result<(), TermixError> {
+        let control = self.control.clone();
+        let Ok(mut state) = control.state.lock() else {
+            return Ok(());
+        };
+        if !state.paused {
+            return Ok(());
+        }
+        state.parked = true;
+        control.cond.notify_all();
+        while state.paused && !state.stopping {
+            state = control.cond.wait(state).unwrap_or_else(|e| e.into_inner());
         }
+        state.parked = false;
+        Ok(())
     }
 
     #[allow(dead_code)]
@@ -96,10 +228,9 @@ impl KeyBoard {
     }
 
     pub fn next_key_timeout(&mut self, timeout: Duration) -> Result<Key, TermixError> {
-        let next_key = if self.next_key.is_some() {
-            self.next_key.take().unwrap()
-        } else {
-            self.next_raw_key_timeout(timeout)?
+        let next_key = match self.next_key.take() {
+            Some(key) => key,
+            None => self.next_raw_key_timeout(timeout)?,
         };
 
         Ok(next_key)
@@ -193,12 +324,8 @@ impl KeyBoard {
             b'H' => Ok(Key::Home),  // khome
             b'F' => Ok(Key::End),
             b'Z' => Ok(Key::BackTab),
-            b'M' => {
-                unimplemented!();
-            }
-            b'<' => {
-                unimplemented!();
-            }
+            b'M' => self.escape_x10_mouse(),
+            b'<' => self.escape_sgr_mouse(),
             _ => Err(TermixError::KeyRead(seq2)),
         }
     }
@@ -212,9 +339,9 @@ impl KeyBoard {
                 b'4' | b'8' => Ok(Key::End), // tmux, xrvt
                 b'5' => Ok(Key::PageUp),     // kpp
                 b'6' => Ok(Key::PageDown),   // knp
-                _ => Err(anyhow::anyhow!("todo! error handle").into()),
+                _ => Err(TermixError::KeyRead(seq2)),
             }
-        } else if (b'0'..=b'9').contains(&seq3) {
+        } else if seq3.is_ascii_digit() {
             let mut str_buf = String::new();
             str_buf.push(seq2 as char);
             str_buf.push(seq3 as char);
@@ -225,18 +352,15 @@ impl KeyBoard {
                 seq_last = self.next_byte_timeout(KEY_WAIT)?;
             }
 
-            match seq_last {
-                b'M' => {
-                    unimplemented!()
-                }
-                b'~' => {
-                    unimplemented!()
-                }
-                _ => unreachable!(),
+            match (str_buf.as_str(), seq_last) {
+                ("200", b'~') => Ok(Key::BracketedPasteStart),
+                ("201", b'~') => Ok(Key::BracketedPasteEnd),
+                // rxvt mouse events and other sequences are not supported
+                _ => Err(TermixError::KeyRead(seq_last)),
             }
         } else if seq3 == b';' {
             let seq4 = self.next_byte_timeout(KEY_WAIT)?;
-            if (b'0'..=b'9').contains(&seq4) {
+            if seq4.is_ascii_digit() {
                 let seq5 = self.next_byte_timeout(KEY_WAIT)?;
                 if seq2 == b'1' {
                     match (seq4, seq5) {
@@ -272,6 +396,45 @@ impl KeyBoard {
             }
         }
     }
+    // ESC [ M Cb Cx Cy, each value is offset by 32
+    fn escape_x10_mouse(&mut self) -> Result<Key, TermixError> {
+        let cb = self.next_byte_timeout(KEY_WAIT)?.saturating_sub(32);
+        let cx = self.next_byte_timeout(KEY_WAIT)?.saturating_sub(32);
+        let cy = self.next_byte_timeout(KEY_WAIT)?.saturating_sub(32);
+        // X10 has no release button, 3 means release
+        let pressed = cb & 0b11 != 3;
+        Ok(mouse_key(
+            cb as u16,
+            (cx as u16).saturating_sub(1),
+            (cy as u16).saturating_sub(1),
+            pressed,
+        ))
+    }
+
+    // ESC [ < Cb ; Cx ; Cy (M | m)
+    fn escape_sgr_mouse(&mut self) -> Result<Key, TermixError> {
+        let mut str_buf = String::new();
+        let mut seq_last = self.next_byte_timeout(KEY_WAIT)?;
+        while seq_last != b'M' && seq_last != b'm' {
+            str_buf.push(seq_last as char);
+            seq_last = self.next_byte_timeout(KEY_WAIT)?;
+        }
+        let nums: Vec<u16> = str_buf
+            .split(';')
+            .map(|n| n.parse::<u16>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| TermixError::KeyRead(seq_last))?;
+        match nums[..] {
+            [cb, cx, cy] => Ok(mouse_key(
+                cb,
+                cx.saturating_sub(1),
+                cy.saturating_sub(1),
+                seq_last == b'M',
+            )),
+            _ => Err(TermixError::KeyRead(seq_last)),
+        }
+    }
+
     fn parse_alt(&mut self, ch: char) -> Result<Key, TermixError> {
         match ch {
             '\u{1B}' => {
@@ -290,7 +453,8 @@ impl KeyBoard {
                     Ok(Key::Right) => Ok(Key::AltRight),
                     Ok(Key::PageUp) => Ok(Key::AltPageUp),
                     Ok(Key::PageDown) => Ok(Key::AltPageDown),
-                    _ => Err(anyhow::anyhow!("todo: erorr handle").into()),
+                    Ok(_) => Err(TermixError::KeyRead(b'[')),
+                    Err(err) => Err(err),
                 }
             }
             '\u{00}' => Ok(Key::CtrlAlt(' ')),
@@ -334,6 +498,9 @@ impl KeyBoard {
         if self.byte_buf.is_empty() {
             self.fetch_bytes(timeout)?;
         }
+        if self.byte_buf.is_empty() {
+            return Err(TermixError::KeyListener);
+        }
 
         Ok(self.byte_buf.remove(0))
     }
@@ -350,10 +517,7 @@ impl KeyBoard {
         let bytes = std::mem::take(&mut self.byte_buf);
         match String::from_utf8(bytes) {
             Ok(string) => {
-                let ret = string
-                    .chars()
-                    .next()
-                    .expect("failed to get next char from input");
+                let ret = string.chars().next().ok_or(TermixError::KeyListener)?;
                 self.byte_buf
                     .extend_from_slice(&string.as_bytes()[ret.len_utf8()..]);
                 Ok(ret)
@@ -362,10 +526,11 @@ impl KeyBoard {
                 let valid_up_to = error.utf8_error().valid_up_to();
                 let bytes = error.into_bytes();
                 let string = String::from_utf8_lossy(&bytes[..valid_up_to]);
-                let ret = string
-                    .chars()
-                    .next()
-                    .expect("failed to get next char from input");
+                let Some(ret) = string.chars().next() else {
+                    // invalid utf-8 sequence, drop the first byte
+                    self.byte_buf.extend_from_slice(&bytes[1..]);
+                    return Err(TermixError::KeyRead(bytes[0]));
+                };
                 self.byte_buf.extend_from_slice(&bytes[ret.len_utf8()..]);
                 Ok(ret)
             }
@@ -373,10 +538,17 @@ impl KeyBoard {
     }
 
     fn fetch_bytes(&mut self, timeout: Duration) -> Result<(), TermixError> {
-        let mut reader_buf = [0; 1];
+        #[cfg(feature = "tokio")]
+        if self.buffered {
+            self.starved = true;
+            return Err(TermixError::KeyListener);
+        }
 
-        // clear interrupt signal
-        while self.sig_rx.read(&mut reader_buf).is_ok() {}
+        self.clear_interrupt();
+        // the request may have been written before it was cleared
+        if self.control.interrupted() {
+            return Err(TermixError::KeyListener);
+        }
 
         wait_until_ready(
             self.file.as_raw_fd(),
@@ -384,14 +556,219 @@ impl KeyBoard {
             timeout,
         )?; // wait timeout
 
-        self.read_unread_bytes();
-        Ok(())
+        self.read_unread_bytes()
     }
 
-    pub fn read_unread_bytes(&mut self) {
+    /// Parses a key from the bytes read so far. Returns `None` if they may be the
+    /// beginning of a longer sequence, unless `complete` tells that nothing follows.
+    #[cfg(feature = "tokio")]
+    fn buffered_key(&mut self, complete: bool) -> Option<Result<Key, TermixError>> {
+        if self.byte_buf.is_empty() {
+            return None;
+        }
+        let read = self.byte_buf.clone();
+        self.buffered = true;
+        self.starved = false;
+        let key = self.next_key_timeout(Duration::new(0, 0));
+        self.buffered = false;
+        if self.starved && !complete {
+            self.byte_buf = read;
+            return None;
+        }
+        Some(key)
+    }
+
+    fn clear_interrupt(&mut self) {
         let mut reader_buf = [0; 1];
-        while let Ok(..) = self.file.read(&mut reader_buf) {
-            self.byte_buf.push(reader_buf[0]);
+        while self.sig_rx.read(&mut reader_buf).is_ok() {}
+    }
+
+    // Reads once, which does not block since `select` reported the input as ready.
+    pub fn read_unread_bytes(&mut self) -> Result<(), TermixError> {
+        let mut reader_buf = [0; 1024];
+        loop {
+            match self.file.read(&mut reader_buf) {
+                Ok(0) => {
+                    // the input was ready but there is nothing to read: end of file
+                    return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+                }
+                Ok(n) => {
+                    self.byte_buf.extend_from_slice(&reader_buf[..n]);
+                    return Ok(());
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Reads keys on the tokio runtime, which waits for the input instead of a thread.
+#[cfg(feature = "tokio")]
+pub struct AsyncKeyBoard {
+    // deregistered before `keyboard` closes the file descriptors
+    input: AsyncFd<RawFd>,
+    interrupt: AsyncFd<RawFd>,
+    keyboard: KeyBoard,
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncKeyBoard {
+    /// Registers the input to the runtime the caller has entered.
+    pub fn new(keyboard: KeyBoard) -> Result<AsyncKeyBoard, TermixError> {
+        let register = |fd| {
+            // the file descriptors are owned by `keyboard`, which is dropped after the registrations
+            unsafe { AsyncFd::register_with_interest(fd, Interest::READABLE) }
+                .map_err(std::io::Error::from)
+        };
+        Ok(AsyncKeyBoard {
+            input: register(keyboard.raw_fd())?,
+            interrupt: register(keyboard.sig_rx.as_raw_fd())?,
+            keyboard,
+        })
+    }
+
+    pub fn control(&self) -> Arc<ListenerControl> {
+        self.keyboard.control()
+    }
+
+    /// Waits while the listener is paused, e.g. while a child process reads the input.
+    pub async fn park_if_paused(&mut self) -> Result<(), TermixError> {
+        loop {
+            {
+                let control = &self.keyboard.control;
+                let Ok(mut state) = control.state.lock() else {
+                    return Ok(());
+                };
+                if !state.paused || state.stopping {
+                    state.parked = false;
+                    return Ok(());
+                }
+                if !state.parked {
+                    state.parked = true;
+                    control.cond.notify_all();
+                }
+            }
+            // woken by `ListenerControl::resume` or `ListenerControl::stop`
+            match self.fetch_bytes().await {
+                Ok(()) | Err(TermixError::KeyListener) => {}
+                Err(err) => return Err(err),
+            }
         }
     }
+
+    /// Returns the next key, or `TermixError::KeyListener` if interrupted by `ListenerControl`.
+    pub async fn next_key(&mut self) -> Result<Key, TermixError> {
+        loop {
+            if self.keyboard.control.interrupted() {
+                return Err(TermixError::KeyListener);
+            }
+            if let Some(key) = self.keyboard.buffered_key(false) {
+                return key;
+            }
+            if self.keyboard.byte_buf.is_empty() {
+                self.fetch_bytes().await?;
+                continue;
+            }
+            // the rest of an escape sequence follows right away, if any
+            match tokio::time::timeout(KEY_WAIT, self.fetch_bytes()).await {
+                Ok(fetched) => fetched?,
+                Err(_) => {
+                    return (self.keyboard.buffered_key(true))
+                        .unwrap_or(Err(TermixError::KeyListener))
+                }
+            }
+        }
+    }
+
+    /// Reads the input once it is ready. Fails with `TermixError::KeyListener` if interrupted
+    /// by `ListenerControl`; the bytes read while paused are left to the child process.
+    async fn fetch_bytes(&mut self) -> Result<(), TermixError> {
+        let AsyncKeyBoard {
+            input,
+            interrupt,
+            keyboard,
+        } = self;
+        std::future::poll_fn(|cx| -> Poll<Result<(), TermixError>> {
+            if let Poll::Ready(ready) = interrupt.poll_read_ready(cx) {
+                keyboard.clear_interrupt();
+                ready?.clear_ready();
+                return Poll::Ready(Err(TermixError::KeyListener));
+            }
+            if keyboard.control.interrupted() {
+                return Poll::Ready(Err(TermixError::KeyListener));
+            }
+            loop {
+                let mut ready = match input.poll_read_ready(cx) {
+                    Poll::Ready(ready) => ready?,
+                    Poll::Pending => return Poll::Pending,
+                };
+                // the input is left in blocking mode, so it is read only if it returns right away
+                if !readable_now(*input.get_ref())? {
+                    ready.clear_ready();
+                    continue;
+                }
+                return Poll::Ready(keyboard.read_unread_bytes());
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "tokio")]
+fn readable_now(fd: RawFd) -> Result<bool, TermixError> {
+    let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+    loop {
+        match poll(&mut fds, 0) {
+            Ok(n) => return Ok(n > 0),
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+// Converts the button code of xterm mouse protocol into a key.
+// Coordinates are 0-based.
+fn mouse_key(cb: u16, col: u16, row: u16, pressed: bool) -> Key {
+    // the bits of modifier keys (shift, meta, ctrl) are ignored
+    let motion = cb & 32 != 0;
+    let wheel = cb & 64 != 0;
+    let button = match cb & 0b11 {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Middle),
+        2 => Some(MouseButton::Right),
+        _ => None,
+    };
+    match (wheel, motion, button) {
+        (true, _, Some(MouseButton::Left)) => Key::WheelUp(row, col),
+        (true, _, _) => Key::WheelDown(row, col),
+        (false, true, Some(_)) => Key::MouseHold(row, col),
+        (false, true, None) => Key::MouseMove(row, col),
+        (false, false, Some(button)) if pressed => Key::MousePress(button, row, col),
+        (false, false, _) => Key::MouseRelease(row, col),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mouse_key;
+    use crate::key::{Key, MouseButton};
+
+    #[test]
+    fn mouse_buttons() {
+        assert!(matches!(
+            mouse_key(0, 4, 2, true),
+            Key::MousePress(MouseButton::Left, 2, 4)
+        ));
+        assert!(matches!(
+            mouse_key(2, 0, 0, true),
+            Key::MousePress(MouseButton::Right, 0, 0)
+        ));
+        assert!(matches!(mouse_key(0, 1, 1, false), Key::MouseRelease(1, 1)));
+        assert!(matches!(mouse_key(3, 1, 1, true), Key::MouseRelease(1, 1)));
+        assert!(matches!(mouse_key(32, 5, 6, true), Key::MouseHold(6, 5)));
+        assert!(matches!(mouse_key(35, 5, 6, true), Key::MouseMove(6, 5)));
+        assert!(matches!(mouse_key(64, 0, 3, true), Key::WheelUp(3, 0)));
+        assert!(matches!(mouse_key(65, 0, 3, true), Key::WheelDown(3, 0)));
+    }
 }
@@ -56,4 +56,19 @@ pub enum Key {
 
     BracketedPasteStart,
     BracketedPasteEnd,
+
+    // (row, col) of the mouse, 0-based
+    MousePress(MouseButton, u16, u16),
+    MouseRelease(u16, u16),
+    MouseHold(u16, u16),
+    MouseMove(u16, u16),
+    WheelUp(u16, u16),
+    WheelDown(u16, u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
 }
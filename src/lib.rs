@@ -23,8 +23,8 @@
 //! use termix::{
 //!     color::{Color, StyledText},
 //!     event::Event,
-//!     model::{ModelAct, Updater},
-//!     Program,
+//!     model::{Cmd, ModelAct, Updater},
+//!     Program, TermixError,
 //! };
 //!
 //! struct Model(usize);
@@ -35,17 +35,19 @@
 //! impl ModelAct<Model, Tick> for Model {
 //!     fn update(&self, event: &Event<Tick>) -> Updater<Model, Tick> {
 //!         match event {
-//!             Event::Init => (Some(Box::new(Model(self.0))), Some(tick)),
 //!             Event::Custom(_) => {
 //!                 if self.0 - 1 == 0 {
-//!                     return (Some(Box::new(Model(self.0 - 1))), Some(|| Event::Quit));
+//!                     return (Some(Box::new(Model(self.0 - 1))), Some(Cmd::quit()));
 //!                 }
-//!                 (Some(Box::new(Model(self.0 - 1))), Some(tick))
+//!                 (Some(Box::new(Model(self.0 - 1))), Some(tick()))
 //!             }
-//!             Event::Keyboard(..) => (None, Some(|| Event::Quit)),
+//!             Event::Keyboard(..) => (None, Some(Cmd::quit())),
 //!             _ => (None, None),
 //!         }
 //!     }
+//!     fn init(&self) -> Option<Cmd<Tick>> {
+//!         Some(tick())
+//!     }
 //!     fn view(&self) -> String {
 //!         StyledText::new(
 //!             &format!(
@@ -62,106 +64,737 @@
 //!     }
 //! }
 //!
-//! fn tick() -> Event<Tick> {
-//!     let one_sec = Duration::from_secs(1);
-//!     std::thread::sleep(one_sec);
-//!     Event::Custom(Tick {})
+//! fn tick() -> Cmd<Tick> {
+//!     Cmd::tick(Duration::from_secs(1), |_| Event::Custom(Tick {}))
 //! }
 //!
-//! fn main() {
-//!     Program::new(Box::new(Model(5))).run();
+//! fn main() -> Result<(), TermixError> {
+//!     Program::new(Box::new(Model(5))).run()?;
+//!     Ok(())
 //! }
 //! ```
 //!
 //! To know how to use termix practically, you can look at the examples
 //!
+//! ## Features
+//!
+//! - `tokio`: the event loop runs on a tokio runtime, which waits for keys, resizes and
+//!   timers as well, and commands are run on it instead of a thread per command.
+//!   `Cmd::future` and `Program::run_async` are available. The runtime is taken from
+//!   `ProgramOptions::runtime`, the runtime the program is run in, or started by the
+//!   program itself when there is none. Only the renderer and the signal dispatcher keep
+//!   their own threads. `Program::run` blocks until the program quits, even in a task
+//!   (with `block_in_place`), while `Program::run_async` is awaited.
+//!   The runtime must be a `multi_thread` one with the time and I/O drivers enabled,
+//!   otherwise `TermixError::Runtime` is returned.
+//!
 pub mod color;
-mod cursor;
+pub mod component;
+mod dispatch;
 mod error;
 pub mod event;
+mod executor;
 mod input;
 pub mod key;
 pub mod model;
+pub mod options;
 mod output;
+mod panic;
+#[cfg(not(feature = "tokio"))]
+mod pool;
 mod raw;
 mod renderer;
+mod signal;
+pub mod testing;
+mod timer;
 
-use model::ModelAct;
+pub use error::TermixError;
+use model::{Model, ModelAct, ModelActAdapter};
+use nix::{
+    sys::signal::{kill, raise, Signal},
+    unistd::Pid,
+};
 use std::{
     fmt::Debug,
-    sync::{
-        mpsc::{channel, Receiver, Sender},
-        Arc, Mutex,
-    },
-    thread,
+    fs::File,
+    io,
+    os::fd::{FromRawFd, RawFd},
+    process::{Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+};
+#[cfg(not(feature = "tokio"))]
+use std::{
+    thread::{self, JoinHandle},
     time::Duration,
 };
+#[cfg(feature = "tokio")]
+use tokio::{
+    runtime::{Handle, RuntimeFlavor},
+    signal::unix::{signal as watch_signal, SignalKind},
+    task::JoinHandle,
+};
 
-use event::Event;
-use input::KeyBoard;
+use dispatch::{Dispatcher, Flow, Host};
+use event::{message_channel, Event, Message, MessageReceiver, MessageSender};
+use executor::Executor;
+#[cfg(feature = "tokio")]
+use executor::{check_runtime, ProgramRuntime};
+#[cfg(feature = "tokio")]
+use input::AsyncKeyBoard;
+use input::{KeyBoard, ListenerControl};
+use model::{Cmd, Control};
+use options::{InputSource, OutputSink, ProgramOptions};
+use raw::terminal_size;
 use renderer::StandardRenderer;
 
-pub struct Program<T, E: Send + Debug> {
-    renderer: Arc<Mutex<StandardRenderer>>,
-    event_tx: Sender<Event<E>>,
-    event_rx: Arc<Receiver<Event<E>>>,
-    model: Arc<Mutex<Box<dyn ModelAct<T, E>>>>,
+pub struct Program<M, E: Send + Debug> {
+    event_tx: MessageSender<E>,
+    event_rx: MessageReceiver<E>,
+    dispatcher: Dispatcher<M, E>,
+    options: ProgramOptions,
+    foreground: Foreground,
 }
 
-impl<T, E: Send + Debug + 'static> Program<T, E> {
+impl<T: 'static, E: Send + Debug + 'static> Program<ModelActAdapter<T, E>, E> {
     /// Initialize models and internals.
-    pub fn new(model: Box<dyn ModelAct<T, E>>) -> Program<T, E> {
-        let (e_tx, e_rx) = channel();
+    pub fn new(model: Box<dyn ModelAct<T, E>>) -> Program<ModelActAdapter<T, E>, E> {
+        Program::with_options(model, ProgramOptions::default())
+    }
+
+    /// Initialize models and internals with the configuration of the terminal session.
+    pub fn with_options(
+        model: Box<dyn ModelAct<T, E>>,
+        options: ProgramOptions,
+    ) -> Program<ModelActAdapter<T, E>, E> {
+        Program::from_model_with_options(ModelActAdapter::new(model), options)
+    }
+}
+
+impl<M: Model<E> + 'static, E: Send + Debug + 'static> Program<M, E> {
+    /// Initialize internals with a model updated in place.
+    pub fn from_model(model: M) -> Program<M, E> {
+        Program::from_model_with_options(model, ProgramOptions::default())
+    }
+
+    /// Same as `from_model`, with the configuration of the terminal session.
+    pub fn from_model_with_options(model: M, options: ProgramOptions) -> Program<M, E> {
+        let (e_tx, e_rx) = message_channel();
         Program {
-            renderer: Arc::new(Mutex::new(StandardRenderer::new())),
             event_tx: e_tx,
-            event_rx: Arc::new(e_rx),
-            model: Arc::new(Mutex::new(model)),
+            event_rx: e_rx,
+            dispatcher: Dispatcher::new(model, &options),
+            options,
+            foreground: Foreground::default(),
         }
     }
 
-    /// Starts UI and event loop
-    pub fn run(&mut self) {
-        self.renderer.lock().unwrap().start();
-        let key_tx = self.event_tx.clone();
-        thread::spawn(move || start_key_listener(key_tx));
-        let tx = self.event_tx.clone();
-        tx.send(Event::Init).unwrap();
-        self.event_loop();
+    /// Adds a filter called with every event before `update`.
+    /// The filter can pass the event, replace it, or drop it by returning `None`.
+    /// Filters run in the order they are added, each receiving the output of the previous one.
+    /// `Event::Quit` is filtered as well, so quitting can be vetoed.
+    ///
+    /// For a `ModelAct`, the filter receives the `ModelActAdapter` driving it.
+    ///
+    /// ```no_run
+    /// # use termix::{event::Event, key::Key, model::{Cmd, Model}, Program};
+    /// struct Editor {
+    ///     unsaved: bool,
+    /// }
+    /// # impl Model<()> for Editor {
+    /// #     fn update(&mut self, _: &Event<()>) -> Option<Cmd<()>> { None }
+    /// #     fn view(&self) -> String { String::new() }
+    /// # }
+    ///
+    /// Program::from_model(Editor { unsaved: true })
+    ///     .filter(|editor, ev| match ev {
+    ///         Event::Quit if editor.unsaved => None,
+    ///         ev => Some(ev),
+    ///     })
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn filter<F>(mut self, f: F) -> Program<M, E>
+    where
+        F: Fn(&M, Event<E>) -> Option<Event<E>> + Send + 'static,
+    {
+        self.dispatcher.filters.push(Box::new(f));
+        self
     }
 
-    fn event_loop(&mut self) {
-        let rx = self.event_rx.clone();
-        while let Ok(ev) = rx.recv() {
-            match &ev {
-                Event::Quit => {
-                    self.renderer.lock().unwrap().quit();
-                    break;
-                }
-                _ => {
-                    if let Ok(mut model) = self.model.lock() {
-                        let (new_model, cmd) = model.update(&ev);
-                        if let Some(new) = new_model {
-                            let _ = std::mem::replace(&mut *model, new);
-                            self.renderer.lock().unwrap().write(&model.view());
-                        }
-                        let tx = self.event_tx.clone();
-                        thread::spawn(move || {
-                            if let Some(cmd) = cmd {
-                                let ev = cmd();
-                                tx.send(ev).unwrap();
-                            }
-                        });
-                    }
+    /// Returns a handle to send events to the program from other threads.
+    pub fn sender(&self) -> ProgramHandle<E> {
+        ProgramHandle {
+            tx: self.event_tx.clone(),
+        }
+    }
+
+    /// Starts UI and event loop.
+    /// Returns the model at the time of quitting, so that the caller can
+    /// use its final state (e.g. the item chosen by the user).
+    /// For a `ModelAct`, use `downcast` to get the concrete model back.
+    ///
+    /// If the program panics, the terminal is restored before the panic message is printed.
+    ///
+    /// Reading keys and rendering are stopped before returning,
+    /// so another program can run right after this one. Commands which are still running
+    /// are not waited for, and their events are dropped. Only the threads running them
+    /// are left behind; idle workers are stopped and joined.
+    ///
+    /// When a signal quits the program (see `ProgramOptions::handle_interrupt`), the terminal
+    /// is restored and the signal is raised again with the disposition it had before the
+    /// program started, so the process is terminated by default. `TermixError::Interrupted`
+    /// is returned only if the signal is handled or ignored by the caller.
+    /// A process started by `Cmd::exec` receives SIGTERM and SIGHUP as well, so that
+    /// the program does not wait for it.
+    ///
+    /// SIGTSTP (e.g. `kill -TSTP`) suspends the program like `Cmd::suspend`. When the process
+    /// is continued after being stopped in any way, the view is repainted and
+    /// `Event::Resume` is fired.
+    ///
+    /// With `tokio` feature, the event loop runs on the runtime and this blocks the
+    /// calling thread until the program quits. Use `run_async` in async code.
+    pub fn run(self) -> Result<M, TermixError> {
+        #[cfg(not(feature = "tokio"))]
+        return self.run_on_threads();
+        #[cfg(feature = "tokio")]
+        return self.block_on_runtime();
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    fn run_on_threads(mut self) -> Result<M, TermixError> {
+        let executor = Executor::new(self.event_tx.clone())
+            .catch_panics(self.options.catch_panics)
+            .workers(self.options.workers);
+        let mut session = self.start()?;
+        let mut terminal = Terminal {
+            renderer: &mut session.renderer,
+            keys: &session.keys,
+            executor: &executor,
+            foreground: &self.foreground,
+            options: &self.options,
+        };
+        let result = event_loop(
+            &mut self.dispatcher,
+            &self.event_rx,
+            &mut terminal,
+            &session.panic,
+        );
+        self.finish(session, &executor, result)
+    }
+
+    #[cfg(feature = "tokio")]
+    fn block_on_runtime(self) -> Result<M, TermixError> {
+        let runtime = ProgramRuntime::new(self.options.runtime.clone())?;
+        // the runtime could not run the tasks of the program while its only thread is blocked
+        if Handle::try_current().is_ok_and(|h| h.runtime_flavor() == RuntimeFlavor::CurrentThread) {
+            return Err(TermixError::Runtime(String::from(
+                "run blocks the current_thread runtime; use run_async",
+            )));
+        }
+        let handle = runtime.handle().clone();
+        tokio::task::block_in_place(|| handle.block_on(self.run_on(handle.clone())))
+    }
+
+    /// Same as `run`, awaited instead of blocking the thread.
+    /// It must be awaited on a tokio runtime, unless `ProgramOptions::runtime` is set.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) -> Result<M, TermixError> {
+        let handle = match self.options.runtime.clone() {
+            Some(handle) => handle,
+            None => Handle::try_current().map_err(|_| {
+                TermixError::Runtime(String::from("run_async is awaited outside of a runtime"))
+            })?,
+        };
+        check_runtime(&handle)?;
+        self.run_on(handle).await
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn run_on(mut self, handle: Handle) -> Result<M, TermixError> {
+        let executor = Executor::with_runtime(self.event_tx.clone(), handle)
+            .catch_panics(self.options.catch_panics)
+            .workers(self.options.workers);
+        let mut session = self.start(executor.handle())?;
+        let mut terminal = Terminal {
+            renderer: &mut session.renderer,
+            keys: &session.keys,
+            executor: &executor,
+            foreground: &self.foreground,
+            options: &self.options,
+        };
+        let result = event_loop(
+            &mut self.dispatcher,
+            &mut self.event_rx,
+            &mut terminal,
+            &session.panic,
+        )
+        .await;
+        session.keys.stop().await;
+        self.finish(session, &executor, result)
+    }
+
+    /// Takes the terminal and starts the sources of events.
+    fn start(&mut self, #[cfg(feature = "tokio")] handle: &Handle) -> Result<Session, TermixError> {
+        // subscribed before entering raw mode, so that the terminal is always restored
+        let signal_tx = self.event_tx.clone();
+        let foreground = self.foreground.clone();
+        let handle_interrupt = self.options.handle_interrupt;
+        let terminate = signal::subscribe(
+            &[Signal::SIGINT, Signal::SIGTERM, Signal::SIGHUP],
+            move |sig| {
+                // the event loop is blocked until the process exits. SIGINT from the
+                // terminal already reaches the process, which is in the foreground
+                if sig == Signal::SIGHUP || (sig == Signal::SIGTERM && !handle_interrupt) {
+                    foreground.signal(sig);
                 }
+                let _ = signal_tx.send(Message::Signal(sig));
+            },
+        )?;
+        let stop_tx = self.event_tx.clone();
+        let foreground = self.foreground.clone();
+        let stop = signal::subscribe(&[Signal::SIGTSTP, Signal::SIGCONT], move |sig| {
+            if !foreground.is_running() {
+                let _ = stop_tx.send(Message::Signal(sig));
+            } else if sig == Signal::SIGTSTP {
+                // stopped along with the process, which has the terminal
+                let _ = raise(Signal::SIGSTOP);
+            }
+        })?;
+        let renderer = StandardRenderer::start(&self.options)?;
+        // the panic message is printed after the terminal is restored
+        let panic = panic::register(renderer.panic_restore());
+        #[cfg(not(feature = "tokio"))]
+        let keys = KeyListener::start(self.event_tx.clone(), &self.options)?;
+        #[cfg(feature = "tokio")]
+        let keys = KeyListener::start(self.event_tx.clone(), &self.options, handle)?;
+        let fd = renderer.raw_fd()?;
+        #[cfg(not(feature = "tokio"))]
+        let resize = watch_resize(self.event_tx.clone(), fd)?;
+        #[cfg(feature = "tokio")]
+        let resize = watch_resize(self.event_tx.clone(), fd, handle)?;
+        let (cols, rows) = terminal_size(fd)?;
+        let _ = self.event_tx.send(Message::Event(Event::Init));
+        let _ = self
+            .event_tx
+            .send(Message::Event(Event::Resize { cols, rows }));
+        Ok(Session {
+            terminate,
+            _stop: stop,
+            renderer,
+            panic,
+            keys,
+            _resize: resize,
+        })
+    }
+
+    /// Stops the sources of events and gives the terminal back.
+    fn finish(
+        self,
+        session: Session,
+        executor: &Executor<E>,
+        result: Result<(), TermixError>,
+    ) -> Result<M, TermixError> {
+        let Session {
+            terminate,
+            mut renderer,
+            keys,
+            ..
+        } = session;
+        // nothing may read the input once the terminal is handed back
+        drop(keys);
+        executor.shutdown();
+        let quit = match result {
+            Err(TermixError::Killed) => renderer.kill(),
+            _ => renderer.quit(),
+        };
+        if let (Err(TermixError::Interrupted), Some(sig)) = (&result, self.dispatcher.signal) {
+            // restores the previous disposition, unless another program subscribes the signal
+            drop(terminate);
+            raise(sig)?;
+        }
+        result.and(quit).map(|_| self.dispatcher.model)
+    }
+}
+
+/// Terminal taken by a running program, and the sources of its events.
+struct Session {
+    terminate: signal::Subscription,
+    _stop: signal::Subscription,
+    renderer: StandardRenderer,
+    panic: panic::Registration,
+    keys: KeyListener,
+    #[cfg(not(feature = "tokio"))]
+    _resize: signal::Subscription,
+    #[cfg(feature = "tokio")]
+    _resize: Task,
+}
+
+#[cfg(not(feature = "tokio"))]
+fn event_loop<M: Model<E>, E: Send + Debug + 'static>(
+    dispatcher: &mut Dispatcher<M, E>,
+    event_rx: &MessageReceiver<E>,
+    terminal: &mut Terminal<'_, E>,
+    panic: &panic::Registration,
+) -> Result<(), TermixError> {
+    panic.run(|| dispatcher.start(terminal))?;
+    while let Ok(msg) = event_rx.recv() {
+        if panic.run(|| dispatcher.dispatch(msg, terminal))? == Flow::Quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+async fn event_loop<M: Model<E>, E: Send + Debug + 'static>(
+    dispatcher: &mut Dispatcher<M, E>,
+    event_rx: &mut MessageReceiver<E>,
+    terminal: &mut Terminal<'_, E>,
+    panic: &panic::Registration,
+) -> Result<(), TermixError> {
+    panic.run(|| dispatcher.start(terminal))?;
+    while let Some(msg) = event_rx.recv().await {
+        if panic.run(|| dispatcher.dispatch(msg, terminal))? == Flow::Quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Fires `Event::Resize` whenever the terminal is resized.
+#[cfg(not(feature = "tokio"))]
+fn watch_resize<E: Send + Debug + 'static>(
+    event_tx: MessageSender<E>,
+    fd: RawFd,
+) -> Result<signal::Subscription, TermixError> {
+    signal::subscribe(&[Signal::SIGWINCH], move |_| {
+        if let Ok((cols, rows)) = terminal_size(fd) {
+            let _ = event_tx.send(Message::Event(Event::Resize { cols, rows }));
+        }
+    })
+}
+
+#[cfg(feature = "tokio")]
+fn watch_resize<E: Send + Debug + 'static>(
+    event_tx: MessageSender<E>,
+    fd: RawFd,
+    handle: &Handle,
+) -> Result<Task, TermixError> {
+    let _guard = handle.enter();
+    let mut resized = watch_signal(SignalKind::window_change())?;
+    Ok(Task(handle.spawn(async move {
+        while resized.recv().await.is_some() {
+            if let Ok((cols, rows)) = terminal_size(fd) {
+                let _ = event_tx.send(Message::Event(Event::Resize { cols, rows }));
             }
         }
+    })))
+}
+
+/// Task of the runtime, aborted when dropped.
+#[cfg(feature = "tokio")]
+struct Task(JoinHandle<()>);
+
+#[cfg(feature = "tokio")]
+impl Drop for Task {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Runs `f`, which blocks until the terminal is taken back, e.g. from a child process.
+/// With `tokio` feature, the other tasks of the runtime keep running meanwhile.
+fn blocking<T>(f: impl FnOnce() -> T) -> T {
+    #[cfg(not(feature = "tokio"))]
+    return f();
+    #[cfg(feature = "tokio")]
+    return tokio::task::block_in_place(f);
+}
+
+/// Terminal of a running program, on which the event loop acts.
+struct Terminal<'a, E: Send + Debug> {
+    renderer: &'a mut StandardRenderer,
+    keys: &'a KeyListener,
+    executor: &'a Executor<E>,
+    foreground: &'a Foreground,
+    options: &'a ProgramOptions,
+}
+
+impl<E: Send + Debug + 'static> Host<E> for Terminal<'_, E> {
+    fn suspend(&mut self) -> Result<(), TermixError> {
+        // the keys are left to the shell until the process is continued
+        self.keys.control.pause();
+        let renderer = &mut self.renderer;
+        let suspended = blocking(|| renderer.control(Control::Suspend));
+        self.keys.control.resume();
+        suspended
+    }
+
+    fn resume(&mut self) -> Result<(), TermixError> {
+        self.renderer.resume()
+    }
+
+    fn control(&mut self, control: Control) -> Result<(), TermixError> {
+        self.renderer.control(control)
+    }
+
+    fn exec(&mut self, mut command: Command) -> Result<io::Result<ExitStatus>, TermixError> {
+        // the process reads the keys until it exits
+        self.keys.control.pause();
+        let output = self.renderer.raw_fd()?;
+        let (input, options, foreground) = (self.keys.input, self.options, self.foreground);
+        let renderer = &mut self.renderer;
+        let status = blocking(|| {
+            renderer.release(|| {
+                attach(options, &mut command, input, output)
+                    .and_then(|_| foreground.run(&mut command))
+            })
+        });
+        self.keys.control.resume();
+        status
+    }
+
+    fn resize(&mut self, cols: u16) {
+        self.renderer.resize(cols);
+    }
+
+    fn render(&mut self, view: String) -> Result<(), TermixError> {
+        self.renderer.write(&view)
+    }
+
+    fn spawn(&mut self, cmd: Cmd<E>) {
+        self.executor.spawn(cmd);
+    }
+}
+
+/// Points the standard streams of the process started by `Cmd::exec` at the input
+/// and the output of the program. They are inherited when the program uses the
+/// terminal of the shell (`InputSource::Tty` and `OutputSink::Stdout`).
+fn attach(
+    options: &ProgramOptions,
+    command: &mut Command,
+    input: RawFd,
+    output: RawFd,
+) -> io::Result<()> {
+    if options.input == InputSource::Tty && options.output == OutputSink::Stdout {
+        return Ok(());
+    }
+    let stdio = |fd| -> io::Result<Stdio> {
+        let fd = nix::unistd::dup(fd)?;
+        Ok(Stdio::from(unsafe { File::from_raw_fd(fd) }))
+    };
+    command
+        .stdin(stdio(input)?)
+        .stdout(stdio(output)?)
+        .stderr(stdio(output)?);
+    Ok(())
+}
+
+/// Cloneable handle to push events into the running program from other threads,
+/// e.g. background workers or sockets.
+/// Events sent after the program has finished are dropped.
+///
+/// ```no_run
+/// # use termix::{event::Event, model::{ModelAct, Updater}, Program};
+/// # struct Model;
+/// # impl ModelAct<Model, String> for Model {
+/// #     fn update(&self, _: &Event<String>) -> Updater<Model, String> { (None, None) }
+/// #     fn view(&self) -> String { String::new() }
+/// # }
+/// let program = Program::new(Box::new(Model));
+/// let handle = program.sender();
+/// std::thread::spawn(move || handle.send(String::from("scanned")));
+/// program.run().unwrap();
+/// ```
+pub struct ProgramHandle<E: Send + Debug> {
+    tx: MessageSender<E>,
+}
+
+impl<E: Send + Debug> Clone for ProgramHandle<E> {
+    fn clone(&self) -> Self {
+        ProgramHandle {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<E: Send + Debug> ProgramHandle<E> {
+    /// Fires `Event::Custom` with the value.
+    pub fn send(&self, event: E) {
+        let _ = self.tx.send(Message::Event(Event::Custom(event)));
+    }
+
+    /// Quits the program gracefully, same as returning `Cmd::quit`.
+    pub fn quit(&self) {
+        let _ = self.tx.send(Message::Event(Event::Quit));
+    }
+
+    /// Fires `Event::Resize`, e.g. for a session on a pty whose size is not
+    /// reported by SIGWINCH of this process.
+    pub fn resize(&self, cols: u16, rows: u16) {
+        let _ = self.tx.send(Message::Event(Event::Resize { cols, rows }));
+    }
+
+    /// Stops the program immediately without rendering the last view.
+    /// `Program::run` returns `TermixError::Killed`.
+    pub fn kill(&self) {
+        let _ = self.tx.send(Message::Kill);
+    }
+}
+
+/// Process started by `Cmd::exec`, which receives the signals quitting the program
+/// while it runs.
+#[derive(Clone, Default)]
+struct Foreground(Arc<Mutex<Option<Pid>>>);
+
+impl Foreground {
+    fn run(&self, command: &mut Command) -> io::Result<ExitStatus> {
+        let mut child = command.spawn()?;
+        if let Ok(mut pid) = self.0.lock() {
+            *pid = Some(Pid::from_raw(child.id() as i32));
+        }
+        let status = child.wait();
+        if let Ok(mut pid) = self.0.lock() {
+            *pid = None;
+        }
+        status
+    }
+
+    fn is_running(&self) -> bool {
+        self.0.lock().is_ok_and(|pid| pid.is_some())
+    }
+
+    fn signal(&self, sig: Signal) {
+        if let Some(pid) = self.0.lock().ok().and_then(|pid| *pid) {
+            let _ = kill(pid, sig);
+        }
+    }
+}
+
+/// Thread reading the keys of a program. It is stopped and joined when dropped.
+#[cfg(not(feature = "tokio"))]
+struct KeyListener {
+    control: Arc<ListenerControl>,
+    // file descriptor the keys are read from
+    input: RawFd,
+    handle: Option<JoinHandle<()>>,
+}
+
+#[cfg(not(feature = "tokio"))]
+impl KeyListener {
+    fn start<E: Send + Debug + 'static>(
+        event_tx: MessageSender<E>,
+        options: &ProgramOptions,
+    ) -> Result<KeyListener, TermixError> {
+        let keyboard = KeyBoard::new(&options.input)?;
+        let control = keyboard.control();
+        let input = keyboard.raw_fd();
+        let handle = thread::spawn(move || start_key_listener(event_tx, keyboard));
+        Ok(KeyListener {
+            control,
+            input,
+            handle: Some(handle),
+        })
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl Drop for KeyListener {
+    fn drop(&mut self) {
+        self.control.stop();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Task reading the keys of a program on the runtime. It is stopped when dropped,
+/// and `stop` waits for it.
+#[cfg(feature = "tokio")]
+struct KeyListener {
+    control: Arc<ListenerControl>,
+    // file descriptor the keys are read from
+    input: RawFd,
+    task: Option<JoinHandle<()>>,
+}
+
+#[cfg(feature = "tokio")]
+impl KeyListener {
+    fn start<E: Send + Debug + 'static>(
+        event_tx: MessageSender<E>,
+        options: &ProgramOptions,
+        handle: &Handle,
+    ) -> Result<KeyListener, TermixError> {
+        let keyboard = KeyBoard::new(&options.input)?;
+        let control = keyboard.control();
+        let input = keyboard.raw_fd();
+        let _guard = handle.enter();
+        let keyboard = AsyncKeyBoard::new(keyboard)?;
+        let task = handle.spawn(listen_keys(event_tx, keyboard));
+        Ok(KeyListener {
+            control,
+            input,
+            task: Some(task),
+        })
+    }
+
+    /// Stops the task and waits until it no longer reads the input.
+    async fn stop(&mut self) {
+        self.control.stop();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for KeyListener {
+    fn drop(&mut self) {
+        // the task is not awaited, e.g. when the program failed to start
+        self.control.stop();
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+fn start_key_listener<E: Send + Debug>(event_tx: MessageSender<E>, mut keyboard: KeyBoard) {
+    let control = keyboard.control();
+    while !control.is_stopping() {
+        let key = keyboard
+            .park_if_paused()
+            .and_then(|_| keyboard.next_key_timeout(Duration::from_secs(0)));
+        let Some(msg) = key_message(key) else {
+            continue;
+        };
+        let fatal = matches!(msg, Message::Error(_));
+        if event_tx.send(msg).is_err() || fatal {
+            break;
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+async fn listen_keys<E: Send + Debug>(event_tx: MessageSender<E>, mut keyboard: AsyncKeyBoard) {
+    let control = keyboard.control();
+    while !control.is_stopping() {
+        let key = match keyboard.park_if_paused().await {
+            Ok(()) => keyboard.next_key().await,
+            Err(err) => Err(err),
+        };
+        let Some(msg) = key_message(key) else {
+            continue;
+        };
+        let fatal = matches!(msg, Message::Error(_));
+        if event_tx.send(msg).is_err() || fatal {
+            break;
+        }
     }
 }
 
-fn start_key_listener<E: Send + Debug>(event_tx: Sender<Event<E>>) {
-    let mut keyboard = KeyBoard::new();
-    while let Ok(key) = keyboard.next_key_timeout(Duration::from_secs(0)) {
-        event_tx.send(Event::Keyboard(key.clone())).unwrap();
+fn key_message<E: Send + Debug>(key: Result<key::Key, TermixError>) -> Option<Message<E>> {
+    match key {
+        Ok(key) => Some(Message::Event(Event::Keyboard(key))),
+        // unknown sequences and interrupted waits are skipped
+        Err(TermixError::KeyRead(_) | TermixError::KeyListener) => None,
+        Err(err) => Some(Message::Error(err)),
     }
 }
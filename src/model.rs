@@ -1,11 +1,315 @@
 //! Trait of model and useful types
 
-use std::fmt::Debug;
+use std::{
+    any::Any,
+    fmt::Debug,
+    io,
+    process::{Command, ExitStatus},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use crate::event::Event;
 
-/// Type of callback function
-pub type Cmd<CustomEvent> = fn() -> Event<CustomEvent>;
+/// Command which is executed by `Program` and fires the next event.
+///
+/// Unlike a bare function pointer, a command can capture state,
+/// so it is possible to fetch "item 42" or load a path the user typed.
+pub struct Cmd<CustomEvent>
+where
+    CustomEvent: Send + Debug,
+{
+    pub(crate) kind: CmdKind<CustomEvent>,
+}
+
+pub(crate) enum CmdKind<CustomEvent>
+where
+    CustomEvent: Send + Debug,
+{
+    Func(Box<dyn FnOnce() -> Event<CustomEvent> + Send + 'static>),
+    Batch(Vec<Cmd<CustomEvent>>),
+    Sequence(Vec<Cmd<CustomEvent>>),
+    Tick {
+        duration: Duration,
+        aligned: bool,
+        f: Box<dyn FnOnce(Instant) -> Event<CustomEvent> + Send + 'static>,
+    },
+    Control(Control),
+    Exec(Command, ExecCallback<CustomEvent>),
+    Cancellable(CancellationToken, Box<Cmd<CustomEvent>>),
+    #[cfg(feature = "tokio")]
+    Future(
+        std::pin::Pin<Box<dyn std::future::Future<Output = Event<CustomEvent>> + Send + 'static>>,
+    ),
+}
+
+pub(crate) type ExecCallback<CustomEvent> =
+    Box<dyn FnOnce(io::Result<ExitStatus>) -> Event<CustomEvent> + Send + 'static>;
+
+type Mapper<CustomEvent, Parent> = Arc<dyn Fn(CustomEvent) -> Parent + Send + Sync + 'static>;
+
+/// Cancels commands started with `Cmd::with_token`, e.g. when the user navigates
+/// away before a search finishes. Cancelled commands which have not started yet
+/// are skipped, and the events of the running ones are dropped.
+/// Long running commands can also check `is_cancelled` to stop early.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Tokens of the commands a command is nested in.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Scope(Vec<CancellationToken>);
+
+impl Scope {
+    pub(crate) fn with(&self, token: CancellationToken) -> Scope {
+        let mut tokens = self.0.clone();
+        tokens.push(token);
+        Scope(tokens)
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.iter().any(CancellationToken::is_cancelled)
+    }
+}
+
+/// Commands handled by `Program` itself instead of the model.
+#[derive(Debug)]
+pub(crate) enum Control {
+    EnterAltScreen,
+    ExitAltScreen,
+    Suspend,
+    Print(String),
+}
+
+impl<CustomEvent> Cmd<CustomEvent>
+where
+    CustomEvent: Send + Debug + 'static,
+{
+    /// Creates a command from a closure. The closure is run on a worker thread
+    /// of the program (on the blocking thread pool of the runtime with `tokio` feature).
+    /// See `ProgramOptions::workers` for how many run at once.
+    pub fn new<F>(f: F) -> Cmd<CustomEvent>
+    where
+        F: FnOnce() -> Event<CustomEvent> + Send + 'static,
+    {
+        Cmd {
+            kind: CmdKind::Func(Box::new(f)),
+        }
+    }
+
+    /// Creates a command from a future, which is run on the tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn future<F>(future: F) -> Cmd<CustomEvent>
+    where
+        F: std::future::Future<Output = Event<CustomEvent>> + Send + 'static,
+    {
+        Cmd {
+            kind: CmdKind::Future(Box::pin(future)),
+        }
+    }
+
+    /// Runs commands concurrently.
+    /// Each resulting event is delivered as soon as its command finishes.
+    pub fn batch(cmds: Vec<Cmd<CustomEvent>>) -> Cmd<CustomEvent> {
+        Cmd {
+            kind: CmdKind::Batch(cmds),
+        }
+    }
+
+    /// Runs commands one by one.
+    /// Each resulting event is delivered before the next command starts.
+    pub fn sequence(cmds: Vec<Cmd<CustomEvent>>) -> Cmd<CustomEvent> {
+        Cmd {
+            kind: CmdKind::Sequence(cmds),
+        }
+    }
+
+    /// Fires the event returned by `f` once `duration` has passed.
+    /// `f` receives the instant the timer fired.
+    ///
+    /// To tick repeatedly, return another tick command from `update`
+    /// when handling the event.
+    pub fn tick<F>(duration: Duration, f: F) -> Cmd<CustomEvent>
+    where
+        F: FnOnce(Instant) -> Event<CustomEvent> + Send + 'static,
+    {
+        Cmd {
+            kind: CmdKind::Tick {
+                duration,
+                aligned: false,
+                f: Box::new(f),
+            },
+        }
+    }
+
+    /// Same as `tick`, but fires at the next wall-clock boundary of `duration`.
+    /// e.g. with one second, the event fires at the beginning of the next second,
+    /// which is useful to keep clocks in sync with the system clock.
+    pub fn every<F>(duration: Duration, f: F) -> Cmd<CustomEvent>
+    where
+        F: FnOnce(Instant) -> Event<CustomEvent> + Send + 'static,
+    {
+        Cmd {
+            kind: CmdKind::Tick {
+                duration,
+                aligned: true,
+                f: Box::new(f),
+            },
+        }
+    }
+
+    /// Command which quits the program.
+    pub fn quit() -> Cmd<CustomEvent> {
+        Cmd::new(|| Event::Quit)
+    }
+
+    /// Switches to the alternate screen (fullscreen mode) while running.
+    pub fn enter_alt_screen() -> Cmd<CustomEvent> {
+        Cmd {
+            kind: CmdKind::Control(Control::EnterAltScreen),
+        }
+    }
+
+    /// Switches back to the primary screen (inline mode) while running.
+    pub fn exit_alt_screen() -> Cmd<CustomEvent> {
+        Cmd {
+            kind: CmdKind::Control(Control::ExitAltScreen),
+        }
+    }
+
+    /// Suspends the program like Ctrl+Z in a shell.
+    /// `Event::Resume` is fired when the program is brought back to the foreground.
+    pub fn suspend() -> Cmd<CustomEvent> {
+        Cmd {
+            kind: CmdKind::Control(Control::Suspend),
+        }
+    }
+
+    /// Prints a line above the inline view, which is kept in the scrollback
+    /// after the view is redrawn. Ignored in the alternate screen.
+    pub fn println(line: impl Into<String>) -> Cmd<CustomEvent> {
+        Cmd {
+            kind: CmdKind::Control(Control::Print(line.into())),
+        }
+    }
+
+    /// Same as `println`, taking `format_args!`.
+    ///
+    /// ```
+    /// # use termix::model::Cmd;
+    /// let cmd: Cmd<()> = Cmd::printf(format_args!("✓ installed {}", "foo"));
+    /// ```
+    pub fn printf(args: std::fmt::Arguments<'_>) -> Cmd<CustomEvent> {
+        Cmd::println(args.to_string())
+    }
+
+    /// Runs an interactive process such as `$EDITOR` in the terminal.
+    /// The view is hidden and the keys are passed to the process until it exits,
+    /// then `callback` is called with its exit status.
+    /// Unless the program uses the terminal of the shell, the standard streams of the
+    /// process are connected to the input and the output of the program, replacing
+    /// the ones set on `command`.
+    ///
+    /// ```no_run
+    /// # use std::process::Command;
+    /// # use termix::{event::Event, model::Cmd};
+    /// let cmd: Cmd<bool> = Cmd::exec(Command::new("vim"), |status| {
+    ///     Event::Custom(status.is_ok_and(|s| s.success()))
+    /// });
+    /// ```
+    pub fn exec<F>(command: Command, callback: F) -> Cmd<CustomEvent>
+    where
+        F: FnOnce(io::Result<ExitStatus>) -> Event<CustomEvent> + Send + 'static,
+    {
+        Cmd {
+            kind: CmdKind::Exec(command, Box::new(callback)),
+        }
+    }
+
+    /// Ties the command to `token`, so that it can be cancelled by `token.cancel()`.
+    ///
+    /// ```
+    /// # use termix::{event::Event, model::{CancellationToken, Cmd}};
+    /// let token = CancellationToken::new();
+    /// let search: Cmd<Vec<String>> = Cmd::new(|| Event::Custom(vec![])).with_token(&token);
+    /// // later, when the query changes
+    /// token.cancel();
+    /// ```
+    pub fn with_token(self, token: &CancellationToken) -> Cmd<CustomEvent> {
+        Cmd {
+            kind: CmdKind::Cancellable(token.clone(), Box::new(self)),
+        }
+    }
+
+    /// Converts the custom events fired by the command, e.g. to embed
+    /// the command of a child component into the parent's event type.
+    pub fn map<Parent, F>(self, f: F) -> Cmd<Parent>
+    where
+        Parent: Send + Debug + 'static,
+        F: Fn(CustomEvent) -> Parent + Send + Sync + 'static,
+    {
+        self.map_arc(Arc::new(f))
+    }
+
+    fn map_arc<Parent>(self, f: Mapper<CustomEvent, Parent>) -> Cmd<Parent>
+    where
+        Parent: Send + Debug + 'static,
+    {
+        let kind = match self.kind {
+            CmdKind::Func(g) => CmdKind::Func(Box::new(move || g().map(&*f))),
+            CmdKind::Batch(cmds) => {
+                CmdKind::Batch(cmds.into_iter().map(|c| c.map_arc(f.clone())).collect())
+            }
+            CmdKind::Sequence(cmds) => {
+                CmdKind::Sequence(cmds.into_iter().map(|c| c.map_arc(f.clone())).collect())
+            }
+            CmdKind::Tick {
+                duration,
+                aligned,
+                f: g,
+            } => CmdKind::Tick {
+                duration,
+                aligned,
+                f: Box::new(move |at| g(at).map(&*f)),
+            },
+            CmdKind::Control(control) => CmdKind::Control(control),
+            CmdKind::Cancellable(token, cmd) => {
+                CmdKind::Cancellable(token, Box::new(cmd.map_arc(f)))
+            }
+            CmdKind::Exec(command, callback) => {
+                CmdKind::Exec(command, Box::new(move |status| callback(status).map(&*f)))
+            }
+            #[cfg(feature = "tokio")]
+            CmdKind::Future(fut) => CmdKind::Future(Box::pin(async move { fut.await.map(&*f) })),
+        };
+        Cmd { kind }
+    }
+}
+
+impl<CustomEvent> Debug for Cmd<CustomEvent>
+where
+    CustomEvent: Send + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cmd").finish_non_exhaustive()
+    }
+}
 
 /// Type of model at next render
 pub type NextModel<Model, CustomEvent> = Option<Box<dyn ModelAct<Model, CustomEvent>>>;
@@ -14,17 +318,220 @@ pub type NextModel<Model, CustomEvent> = Option<Box<dyn ModelAct<Model, CustomEv
 pub type Updater<Model, CustomEvent> = (NextModel<Model, CustomEvent>, Option<Cmd<CustomEvent>>);
 
 /// ModelAct must be implemented to pass Program
-/// One big difference with bubbletea, there isn't init method.
-/// To handle model and command initialization, you must write logic in
-/// `update` method.
-pub trait ModelAct<Model, CustomEvent>
+/// Like bubbletea, the command to run on start is returned by `init`,
+/// which does nothing by default. `Event::Init` is still fired to `update`
+/// to initialize the model itself.
+pub trait ModelAct<Model, CustomEvent>: Any
 where
     CustomEvent: Send + Debug,
 {
     /// Define logic how to handle models.
     /// Updater.0 is model with state on next render
-    /// Updater.1 is callback function to fire next event
+    /// Updater.1 is command to fire next event
     fn update(&self, event: &Event<CustomEvent>) -> Updater<Model, CustomEvent>;
     /// Define UI
     fn view(&self) -> String;
+    /// Command to run when the program starts. Called before the first render.
+    fn init(&self) -> Option<Cmd<CustomEvent>> {
+        None
+    }
+}
+
+impl<Model, CustomEvent> dyn ModelAct<Model, CustomEvent>
+where
+    Model: 'static,
+    CustomEvent: Send + Debug + 'static,
+{
+    /// Converts the model returned by `Program::run` back to its concrete type.
+    pub fn downcast(self: Box<Self>) -> Result<Box<Model>, Box<Self>> {
+        if (&*self as &dyn Any).is::<Model>() {
+            let any: Box<dyn Any> = self;
+            Ok(any.downcast().expect("type is checked"))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// Model updated in place, so that the state is not rebuilt on every event.
+///
+/// ```no_run
+/// use termix::{event::Event, key::Key, model::{Cmd, Model}, Program};
+///
+/// struct Counter(u64);
+///
+/// impl Model<()> for Counter {
+///     fn update(&mut self, event: &Event<()>) -> Option<Cmd<()>> {
+///         match event {
+///             Event::Keyboard(Key::Char('q')) => Some(Cmd::quit()),
+///             Event::Keyboard(_) => {
+///                 self.0 += 1;
+///                 None
+///             }
+///             _ => None,
+///         }
+///     }
+///     fn view(&self) -> String {
+///         format!("{} keys pressed\n", self.0)
+///     }
+/// }
+///
+/// let counter = Program::from_model(Counter(0)).run().unwrap();
+/// println!("{}", counter.0);
+/// ```
+pub trait Model<CustomEvent>
+where
+    CustomEvent: Send + Debug,
+{
+    /// Applies the event to the model and returns the command to fire next event.
+    fn update(&mut self, event: &Event<CustomEvent>) -> Option<Cmd<CustomEvent>>;
+    /// Define UI
+    fn view(&self) -> String;
+    /// Command to run when the program starts. Called before the first render.
+    fn init(&self) -> Option<Cmd<CustomEvent>> {
+        None
+    }
+    /// Whether the view has to be rendered again after the last `update`.
+    fn changed(&self) -> bool {
+        true
+    }
+}
+
+/// Drives a `ModelAct` as a `Model`. The view is rendered only when
+/// `ModelAct::update` returns a next model.
+pub struct ModelActAdapter<Model, CustomEvent>
+where
+    CustomEvent: Send + Debug,
+{
+    model: Box<dyn ModelAct<Model, CustomEvent>>,
+    changed: bool,
+}
+
+impl<Model, CustomEvent> ModelActAdapter<Model, CustomEvent>
+where
+    Model: 'static,
+    CustomEvent: Send + Debug + 'static,
+{
+    pub fn new(model: Box<dyn ModelAct<Model, CustomEvent>>) -> Self {
+        ModelActAdapter {
+            model,
+            changed: false,
+        }
+    }
+
+    /// Current model, e.g. for `Program::filter`.
+    pub fn model(&self) -> &dyn ModelAct<Model, CustomEvent> {
+        &*self.model
+    }
+
+    pub fn into_inner(self) -> Box<dyn ModelAct<Model, CustomEvent>> {
+        self.model
+    }
+
+    /// Converts the model back to its concrete type.
+    pub fn downcast(self) -> Result<Box<Model>, Box<dyn ModelAct<Model, CustomEvent>>> {
+        self.model.downcast()
+    }
+}
+
+impl<M, CustomEvent> Model<CustomEvent> for ModelActAdapter<M, CustomEvent>
+where
+    M: 'static,
+    CustomEvent: Send + Debug + 'static,
+{
+    fn update(&mut self, event: &Event<CustomEvent>) -> Option<Cmd<CustomEvent>> {
+        let (next, cmd) = self.model.update(event);
+        self.changed = next.is_some();
+        if let Some(next) = next {
+            self.model = next;
+        }
+        cmd
+    }
+
+    fn view(&self) -> String {
+        self.model.view()
+    }
+
+    fn init(&self) -> Option<Cmd<CustomEvent>> {
+        self.model.init()
+    }
+
+    fn changed(&self) -> bool {
+        self.changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cmd, CmdKind, Model, ModelAct, ModelActAdapter, Updater};
+    use crate::event::Event;
+
+    struct Picker(usize);
+    struct Other;
+
+    impl ModelAct<Picker, ()> for Picker {
+        fn update(&self, _: &Event<()>) -> Updater<Picker, ()> {
+            (None, None)
+        }
+        fn view(&self) -> String {
+            String::new()
+        }
+    }
+
+    impl ModelAct<Picker, ()> for Other {
+        fn update(&self, _: &Event<()>) -> Updater<Picker, ()> {
+            (None, None)
+        }
+        fn view(&self) -> String {
+            String::new()
+        }
+    }
+
+    #[test]
+    fn downcast_to_concrete_model() {
+        let model: Box<dyn ModelAct<Picker, ()>> = Box::new(Picker(42));
+        assert_eq!(model.downcast().ok().map(|m| m.0), Some(42));
+
+        let model: Box<dyn ModelAct<Picker, ()>> = Box::new(Other);
+        assert!(model.downcast().is_err());
+    }
+
+    #[test]
+    fn adapter_reports_change() {
+        struct Toggle(bool);
+        impl ModelAct<Toggle, ()> for Toggle {
+            fn update(&self, event: &Event<()>) -> Updater<Toggle, ()> {
+                match event {
+                    Event::Init => (Some(Box::new(Toggle(!self.0))), None),
+                    _ => (None, None),
+                }
+            }
+            fn view(&self) -> String {
+                self.0.to_string()
+            }
+        }
+        let mut adapter = ModelActAdapter::new(Box::new(Toggle(false)));
+        adapter.update(&Event::Init);
+        assert!(adapter.changed());
+        assert_eq!(adapter.view(), "true");
+        adapter.update(&Event::Resume);
+        assert!(!adapter.changed());
+        assert!(adapter.downcast().is_ok_and(|t| t.0));
+    }
+
+    #[test]
+    fn map_fired_events() {
+        let cmd = Cmd::sequence(vec![Cmd::new(|| Event::Custom(1u8)), Cmd::quit()]);
+        let CmdKind::Sequence(cmds) = cmd.map(|n| u32::from(n) * 2).kind else {
+            unreachable!()
+        };
+        let events: Vec<Event<u32>> = cmds
+            .into_iter()
+            .map(|cmd| match cmd.kind {
+                CmdKind::Func(f) => f(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert!(matches!(events[..], [Event::Custom(2), Event::Quit]));
+    }
 }
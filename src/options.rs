@@ -0,0 +1,247 @@
+//! Options to configure the terminal session of `Program`.
+
+use std::{
+    fmt::Debug,
+    io::{Read, Write},
+    os::fd::AsRawFd,
+    sync::{Arc, Mutex},
+};
+
+use crate::error::TermixError;
+
+/// Which mouse events are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MouseMode {
+    /// Mouse events are not reported.
+    #[default]
+    Disabled,
+    /// Clicks, wheel and motion while a button is held are reported.
+    CellMotion,
+    /// All motion is reported, even when no button is held.
+    AllMotion,
+}
+
+/// Where keys are read from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum InputSource {
+    /// Reads from the controlling terminal (`/dev/tty`).
+    #[default]
+    Tty,
+    /// Reads from the standard input.
+    Stdin,
+    /// Reads from the given stream, e.g. the slave side of a pty. See `InputSource::custom`.
+    Custom(Stream<dyn InputStream>),
+}
+
+impl InputSource {
+    /// Reads keys from `input`. Its file descriptor is waited with `select`,
+    /// and its flags are left untouched, so it can share a pty with the output.
+    pub fn custom(input: impl InputStream + 'static) -> InputSource {
+        InputSource::Custom(Stream::new(Box::new(input)))
+    }
+}
+
+/// Where the UI is drawn.
+/// Drawing to `Stderr` or `Tty` keeps the standard output clean,
+/// e.g. to print the selection of a picker after the program quits.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum OutputSink {
+    #[default]
+    Stdout,
+    Stderr,
+    /// Draws to the controlling terminal (`/dev/tty`), even if stdout and stderr are redirected.
+    Tty,
+    /// Draws to the given stream. See `OutputSink::custom`.
+    Custom(Stream<dyn OutputStream>),
+}
+
+impl OutputSink {
+    /// Draws to `output`, which must be a terminal since it is switched to raw mode.
+    pub fn custom(output: impl OutputStream + 'static) -> OutputSink {
+        OutputSink::Custom(Stream::new(Box::new(output)))
+    }
+}
+
+pub trait InputStream: Read + AsRawFd + Send {}
+
+impl<T: Read + AsRawFd + Send> InputStream for T {}
+
+pub trait OutputStream: Write + AsRawFd + Send {}
+
+impl<T: Write + AsRawFd + Send> OutputStream for T {}
+
+/// Stream shared by the clones of `ProgramOptions`.
+/// Only the first program run with the options can use it.
+pub struct Stream<T: ?Sized>(Arc<Mutex<Option<Box<T>>>>);
+
+impl<T: ?Sized> Stream<T> {
+    fn new(stream: Box<T>) -> Stream<T> {
+        Stream(Arc::new(Mutex::new(Some(stream))))
+    }
+
+    pub(crate) fn take(&self) -> Result<Box<T>, TermixError> {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|mut s| s.take())
+            .ok_or(TermixError::StreamInUse)
+    }
+}
+
+impl<T: ?Sized> Clone for Stream<T> {
+    fn clone(&self) -> Self {
+        Stream(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> Debug for Stream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Stream").finish_non_exhaustive()
+    }
+}
+
+impl<T: ?Sized> PartialEq for Stream<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T: ?Sized> Eq for Stream<T> {}
+
+/// Configuration of `Program`.
+///
+/// ```no_run
+/// # use termix::options::{MouseMode, ProgramOptions};
+/// let options = ProgramOptions::new()
+///     .alt_screen(true)
+///     .mouse(MouseMode::CellMotion)
+///     .fps(30);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProgramOptions {
+    pub(crate) alt_screen: bool,
+    pub(crate) mouse: MouseMode,
+    pub(crate) bracketed_paste: bool,
+    pub(crate) fps: u32,
+    pub(crate) input: InputSource,
+    pub(crate) output: OutputSink,
+    pub(crate) clear_on_exit: bool,
+    pub(crate) handle_interrupt: bool,
+    pub(crate) suspend_on_ctrl_z: bool,
+    pub(crate) catch_panics: bool,
+    pub(crate) workers: usize,
+    #[cfg(feature = "tokio")]
+    pub(crate) runtime: Option<tokio::runtime::Handle>,
+}
+
+impl Default for ProgramOptions {
+    fn default() -> Self {
+        ProgramOptions {
+            alt_screen: false,
+            mouse: MouseMode::Disabled,
+            bracketed_paste: false,
+            fps: ProgramOptions::DEFAULT_FPS,
+            input: InputSource::Tty,
+            output: OutputSink::Stdout,
+            clear_on_exit: false,
+            handle_interrupt: false,
+            suspend_on_ctrl_z: false,
+            catch_panics: false,
+            workers: ProgramOptions::DEFAULT_WORKERS,
+            #[cfg(feature = "tokio")]
+            runtime: None,
+        }
+    }
+}
+
+impl ProgramOptions {
+    const DEFAULT_FPS: u32 = 60;
+    const MAX_FPS: u32 = 120;
+    pub(crate) const DEFAULT_WORKERS: usize = 16;
+
+    pub fn new() -> ProgramOptions {
+        ProgramOptions::default()
+    }
+
+    /// Renders in the alternate screen, so the shell history is kept intact.
+    pub fn alt_screen(mut self, enabled: bool) -> ProgramOptions {
+        self.alt_screen = enabled;
+        self
+    }
+
+    /// Reports mouse events as `Key::Mouse*` keys.
+    pub fn mouse(mut self, mode: MouseMode) -> ProgramOptions {
+        self.mouse = mode;
+        self
+    }
+
+    /// Wraps pasted text with `Key::BracketedPasteStart` and `Key::BracketedPasteEnd`.
+    pub fn bracketed_paste(mut self, enabled: bool) -> ProgramOptions {
+        self.bracketed_paste = enabled;
+        self
+    }
+
+    /// Maximum number of frames rendered per second. Clamped to 1..=120.
+    pub fn fps(mut self, fps: u32) -> ProgramOptions {
+        self.fps = fps.clamp(1, ProgramOptions::MAX_FPS);
+        self
+    }
+
+    pub fn input(mut self, input: InputSource) -> ProgramOptions {
+        self.input = input;
+        self
+    }
+
+    pub fn output(mut self, output: OutputSink) -> ProgramOptions {
+        self.output = output;
+        self
+    }
+
+    /// Delivers `Event::Interrupt` on SIGINT and SIGTERM instead of quitting,
+    /// so that the model can veto it (e.g. when there are unsaved changes).
+    /// SIGHUP always quits since the terminal is gone.
+    ///
+    /// When the program quits by a signal, the signal is raised again after the terminal
+    /// is restored. See `Program::run`.
+    pub fn handle_interrupt(mut self, enabled: bool) -> ProgramOptions {
+        self.handle_interrupt = enabled;
+        self
+    }
+
+    /// Suspends the program on Ctrl+Z instead of passing the key to the model.
+    pub fn suspend_on_ctrl_z(mut self, enabled: bool) -> ProgramOptions {
+        self.suspend_on_ctrl_z = enabled;
+        self
+    }
+
+    /// Erases the last rendered view when the program quits.
+    pub fn clear_on_exit(mut self, enabled: bool) -> ProgramOptions {
+        self.clear_on_exit = enabled;
+        self
+    }
+
+    /// Delivers panics of commands to the model as `Event::Panic` and keeps running.
+    /// Otherwise the program quits with `TermixError::Panic`.
+    pub fn catch_panics(mut self, enabled: bool) -> ProgramOptions {
+        self.catch_panics = enabled;
+        self
+    }
+
+    /// Maximum number of commands run at once. Others wait until a worker is free.
+    /// Defaults to 16. With `tokio` feature, this limits the blocking commands
+    /// made by `Cmd::new`, while futures are scheduled by the runtime.
+    pub fn workers(mut self, workers: usize) -> ProgramOptions {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Runtime on which the event loop, the keys, the resizes and the commands run.
+    /// Defaults to the runtime `Program::run` is called in, or a runtime owned by
+    /// the program when there is none.
+    /// It must be a `multi_thread` runtime with the time and I/O drivers enabled.
+    #[cfg(feature = "tokio")]
+    pub fn runtime(mut self, handle: tokio::runtime::Handle) -> ProgramOptions {
+        self.runtime = Some(handle);
+        self
+    }
+}
@@ -1,17 +1,66 @@
-use std::io::{Stdout, Write};
+use std::{
+    io::{self, Write},
+    os::fd::{AsRawFd, RawFd},
+};
 
-use crate::raw::{IntoRawMode, RawTerminal};
+use unicode_width::UnicodeWidthStr;
 
-trait Flushable {
-    fn flush();
+use crate::{
+    error::TermixError,
+    options::{MouseMode, OutputSink, OutputStream, ProgramOptions},
+    raw::{get_tty, IntoRawMode, RawTerminal},
+};
+
+/// Writer the UI is drawn to.
+pub struct Sink(Box<dyn OutputStream>);
+
+impl Sink {
+    pub fn new(sink: &OutputSink) -> Result<Sink, TermixError> {
+        let stream: Box<dyn OutputStream> = match sink {
+            OutputSink::Stdout => Box::new(io::stdout()),
+            OutputSink::Stderr => Box::new(io::stderr()),
+            OutputSink::Tty => get_tty()?,
+            OutputSink::Custom(stream) => stream.take()?,
+        };
+        Ok(Sink(stream))
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl AsRawFd for Sink {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
 }
 
 pub struct Output {
-    buf: Vec<u8>,
-    out_target: RawTerminal<Stdout>,
+    // frame waiting to be painted
+    buf: Option<Vec<u8>>,
+    out_target: RawTerminal<Sink>,
     lines: usize,
+    // width of the terminal, 0 if unknown
+    cols: u16,
+    options: ProgramOptions,
+    // whether the alternate screen is currently used
+    alt_screen: bool,
+    // lines of the inline view hidden behind the alternate screen
+    inline_lines: usize,
+    // kept to repaint when switching screens
+    last_frame: Vec<u8>,
+    // lines to print above the inline view on the next flush
+    printed: Vec<String>,
+    // the terminal is restored, so nothing is written anymore
+    finished: bool,
 }
-unsafe impl Send for Output {}
 
 impl std::fmt::Debug for Output {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -22,43 +71,305 @@ impl std::fmt::Debug for Output {
     }
 }
 
-impl Output {
-    pub fn new() -> Output {
-        let mut raw = std::io::stdout().into_raw_mode().unwrap();
-        // hide cursor
-        raw.hide_cursor().unwrap();
-        raw.flush().unwrap();
+impl Drop for Output {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.restore();
+        }
+    }
+}
 
-        Output {
-            buf: vec![],
+impl Output {
+    pub fn new(options: &ProgramOptions) -> Result<Output, TermixError> {
+        let raw = Sink::new(&options.output)?.into_raw_mode()?;
+        let mut output = Output {
+            buf: None,
             out_target: raw,
             lines: 0,
+            cols: 0,
+            options: options.clone(),
+            alt_screen: options.alt_screen,
+            inline_lines: 0,
+            last_frame: vec![],
+            printed: vec![],
+            finished: false,
+        };
+        output.setup()?;
+        Ok(output)
+    }
+
+    /// Enables the terminal features configured by the options.
+    fn setup(&mut self) -> Result<(), TermixError> {
+        if self.alt_screen {
+            self.out_target.write_all(b"\x1b[?1049h")?;
+        }
+        match self.options.mouse {
+            MouseMode::Disabled => {}
+            MouseMode::CellMotion => self.out_target.write_all(b"\x1b[?1002h\x1b[?1006h")?,
+            MouseMode::AllMotion => self.out_target.write_all(b"\x1b[?1003h\x1b[?1006h")?,
+        }
+        if self.options.bracketed_paste {
+            self.out_target.write_all(b"\x1b[?2004h")?;
         }
+        // hide cursor
+        self.out_target.hide_cursor()?;
+        self.out_target.flush()?;
+        Ok(())
     }
+    /// Replaces the pending frame, so only the latest view is painted.
     pub fn write(&mut self, new_data: &str) {
-        self.buf.extend(new_data.as_bytes());
+        if self.finished {
+            return;
+        }
+        self.buf = Some(new_data.as_bytes().to_vec());
+    }
+
+    /// Queues a line to be printed above the inline view. Ignored in the alternate screen.
+    pub fn print(&mut self, line: &str) {
+        if !self.alt_screen {
+            self.printed.push(line.to_string());
+        }
+    }
+
+    /// Whether there is something to paint on the next flush.
+    pub fn dirty(&self) -> bool {
+        self.buf.is_some() || !self.printed.is_empty()
+    }
+
+    pub fn flush(&mut self) -> Result<(), TermixError> {
+        if self.finished || !self.dirty() {
+            return Ok(());
+        }
+        if !self.printed.is_empty() {
+            // the printed lines go to the scrollback, and the view is drawn below them
+            self.clear_lines()?;
+            self.lines = 0;
+            for line in std::mem::take(&mut self.printed) {
+                self.out_target.write_all(line.as_bytes())?;
+                self.out_target.write_all(b"\n")?;
+            }
+        }
+        let frame = self.buf.take().unwrap_or_else(|| self.last_frame.clone());
+        self.paint(&frame)?;
+        self.last_frame = frame;
+        Ok(())
+    }
+
+    fn paint(&mut self, frame: &[u8]) -> Result<(), TermixError> {
+        if self.alt_screen {
+            // home the cursor and overwrite the last frame line by line,
+            // clearing what is left of each line and below the frame
+            let frame = String::from_utf8_lossy(frame).replace('\n', "\x1b[K\n");
+            self.out_target.write_all(b"\x1b[H")?;
+            self.out_target.write_all(frame.as_bytes())?;
+            self.out_target.write_all(b"\x1b[K\x1b[0J")?;
+        } else {
+            self.clear_lines()?;
+            self.lines = count_lines(&String::from_utf8_lossy(frame), self.cols);
+            self.out_target.write_all(frame)?;
+        }
+        self.out_target.flush()?;
+        Ok(())
+    }
+
+    /// Erases the last rendered view.
+    fn clear_lines(&mut self) -> Result<(), TermixError> {
+        for _ in 0..self.lines {
+            self.out_target.write_all(b"\x1bM")?;
+        }
+        self.out_target.flush()?;
+        self.out_target.write_all(b"\x1b[0J")?;
+        self.out_target.flush()?;
+        Ok(())
+    }
+
+    pub fn raw_fd(&self) -> RawFd {
+        self.out_target.as_raw_fd()
+    }
+
+    pub fn resize(&mut self, cols: u16) {
+        self.cols = cols;
+    }
+
+    pub fn enter_alt_screen(&mut self) -> Result<(), TermixError> {
+        if self.alt_screen {
+            return Ok(());
+        }
+        self.out_target.write_all(b"\x1b[?1049h")?;
+        self.alt_screen = true;
+        self.inline_lines = self.lines;
+        self.repaint()
+    }
+
+    pub fn exit_alt_screen(&mut self) -> Result<(), TermixError> {
+        if !self.alt_screen {
+            return Ok(());
+        }
+        self.out_target.write_all(b"\x1b[?1049l")?;
+        self.alt_screen = false;
+        // the cursor is restored below the inline view drawn before entering
+        self.lines = self.inline_lines;
+        self.repaint()
+    }
+
+    fn repaint(&mut self) -> Result<(), TermixError> {
+        if self.buf.is_some() {
+            return self.flush();
+        }
+        let frame = std::mem::take(&mut self.last_frame);
+        let painted = self.paint(&frame);
+        self.last_frame = frame;
+        painted
+    }
+
+    /// Gives the terminal back to the shell, keeping the state to resume.
+    pub fn suspend(&mut self) -> Result<(), TermixError> {
+        self.flush()?;
+        let alt_screen = self.alt_screen;
+        self.restore()?;
+        // entered again on resume
+        self.alt_screen = alt_screen;
+        Ok(())
+    }
+
+    /// Takes the terminal again and repaints the last view.
+    pub fn resume(&mut self) -> Result<(), TermixError> {
+        self.out_target.resume_raw()?;
+        self.setup()?;
+        // the shell may have printed something, so the view is drawn from the current line
+        self.lines = 0;
+        self.inline_lines = 0;
+        self.repaint()
+    }
+
+    pub fn quit(&mut self) -> Result<(), TermixError> {
+        // e.g. killed by the panic hook, whose message must not be painted over
+        if self.finished {
+            return Ok(());
+        }
+        // render the final view
+        self.flush()?;
+        if self.options.clear_on_exit && !self.alt_screen {
+            self.clear_lines()?;
+            self.lines = 0;
+        }
+        self.finished = true;
+        self.restore()
+    }
+
+    pub fn kill(&mut self) -> Result<(), TermixError> {
+        if self.finished {
+            return Ok(());
+        }
+        self.buf = None;
+        self.finished = true;
+        self.restore()
     }
 
-    pub fn flush(&mut self) {
-        if !self.buf.is_empty() {
-            for _ in 0..self.lines {
-                let _ = self.out_target.write(b"\x1bM");
+    /// Disables the terminal features enabled by the program and leaves the raw mode.
+    fn restore(&mut self) -> Result<(), TermixError> {
+        if self.options.bracketed_paste {
+            self.out_target.write_all(b"\x1b[?2004l")?;
+        }
+        match self.options.mouse {
+            MouseMode::Disabled => {}
+            MouseMode::CellMotion => self.out_target.write_all(b"\x1b[?1002l\x1b[?1006l")?,
+            MouseMode::AllMotion => self.out_target.write_all(b"\x1b[?1003l\x1b[?1006l")?,
+        }
+        if self.alt_screen {
+            self.out_target.write_all(b"\x1b[?1049l")?;
+            self.alt_screen = false;
+        }
+        self.out_target.finish_raw()
+    }
+}
+
+/// Removes escape sequences such as SGR from the text.
+pub(crate) fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\x1b' {
+            stripped.push(ch);
+            continue;
+        }
+        // other sequences than CSI consist of one character after ESC
+        if chars.next() == Some('[') {
+            // CSI sequence ends with a character in '@'..='~'
+            for ch in chars.by_ref() {
+                if ('@'..='~').contains(&ch) {
+                    break;
+                }
             }
-            let _ = self.out_target.flush();
-            let _ = self.out_target.write(b"\x1b[0J");
-            let _ = self.out_target.flush();
-            self.lines = std::str::from_utf8(&self.buf)
-                .unwrap_or("")
-                .replace("\x1b[0m", "")
-                .lines()
-                .count();
-        }
-        let _ = self.out_target.write(&self.buf);
-        self.buf.clear();
-        let _ = self.out_target.flush();
-    }
-
-    pub fn quit(&mut self) {
-        self.out_target.finish_raw().unwrap();
+        }
+    }
+    stripped
+}
+
+/// Counts the rows the text occupies on the terminal, including wrapped lines.
+fn count_lines(frame: &str, cols: u16) -> usize {
+    strip_ansi(frame)
+        .lines()
+        .map(|line| {
+            let width = line.width();
+            if cols == 0 || width == 0 {
+                1
+            } else {
+                width.div_ceil(cols as usize)
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Read, os::fd::FromRawFd};
+
+    use nix::pty::openpty;
+
+    use super::{count_lines, strip_ansi, Output};
+    use crate::options::{OutputSink, ProgramOptions};
+
+    #[test]
+    fn strip_sgr() {
+        assert_eq!(strip_ansi("\x1b[38;5;212m\x1b[49mHi\x1b[0m"), "Hi");
+        assert_eq!(strip_ansi("a\x1bMb"), "ab");
+    }
+
+    #[test]
+    fn count_wrapped_lines() {
+        assert_eq!(count_lines("abc\ndef\n", 0), 2);
+        assert_eq!(count_lines("abc\n\ndef\n", 3), 3);
+        assert_eq!(count_lines("abcdefg\n", 3), 3);
+        assert_eq!(count_lines("\x1b[1mabc\x1b[0m\n", 3), 1);
+        // wide characters take two columns
+        assert_eq!(count_lines("あいう\n", 4), 2);
+    }
+
+    #[test]
+    fn nothing_written_after_kill() {
+        let pty = openpty(None, None).unwrap();
+        let mut master = unsafe { File::from_raw_fd(pty.master) };
+        let slave = unsafe { File::from_raw_fd(pty.slave) };
+        let options = ProgramOptions::new()
+            .output(OutputSink::custom(slave))
+            .clear_on_exit(true);
+        let mut o = Output::new(&options).unwrap();
+        o.write("before\n");
+        o.flush().unwrap();
+        // as the panic hook does, before the program quits
+        o.kill().unwrap();
+        o.write("after\n");
+        o.flush().unwrap();
+        o.quit().unwrap();
+        drop(o);
+        // the slave is closed, so reading stops once everything is read
+        let mut screen = vec![];
+        let _ = master.read_to_end(&mut screen);
+        let screen = String::from_utf8_lossy(&screen);
+        assert!(screen.contains("before"));
+        assert!(!screen.contains("after"));
+        // the view is not cleared
+        assert!(!screen.contains("\x1bM"));
     }
 }
@@ -0,0 +1,184 @@
+//! Process-wide panic handling.
+//!
+//! A panic hook restores the terminal of a running program before the
+//! panic message is printed, so that it is not mangled by the raw mode.
+//! Only panics ending the program restore it: those of its event loop, and
+//! those of commands caught by `catch` without `silent`. Other panics, e.g. of
+//! threads of the user, may be recovered from, so the program keeps running.
+//! Panics caught by `catch` with `silent` skip the hook entirely; they are
+//! reported to the program as events instead.
+
+use std::{
+    any::Any,
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, Once,
+    },
+};
+
+type Restore = Box<dyn Fn() + Send + 'static>;
+
+struct Terminal {
+    id: u64,
+    restore: Restore,
+}
+
+/// How the panics of the current thread are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Catching {
+    No,
+    // reported to the program as an event
+    Silent,
+    // quits the program
+    Fatal,
+}
+
+static HOOK: Once = Once::new();
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static TERMINALS: Mutex<Vec<Terminal>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static CATCHING: Cell<Catching> = const { Cell::new(Catching::No) };
+    // program whose event loop runs on the current thread
+    static RUNNING: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+fn install_hook() {
+    HOOK.call_once(|| {
+        let prev = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let catching = CATCHING.get();
+            if catching == Catching::Silent {
+                return;
+            }
+            let running = RUNNING.get();
+            let terminals = TERMINALS.lock().unwrap_or_else(|e| e.into_inner());
+            // the program of a command is unknown, so all of them are restored
+            terminals
+                .iter()
+                .filter(|t| catching == Catching::Fatal || running == Some(t.id))
+                .for_each(|t| (t.restore)());
+            drop(terminals);
+            prev(info);
+        }));
+    });
+}
+
+/// Unregisters the terminal when dropped.
+pub struct Registration {
+    id: u64,
+}
+
+/// Calls `restore` when the event loop run by `Registration::run` panics,
+/// or a command panics without being caught while the registration is alive.
+pub fn register<F>(restore: F) -> Registration
+where
+    F: Fn() + Send + 'static,
+{
+    install_hook();
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    TERMINALS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Terminal {
+            id,
+            restore: Box::new(restore),
+        });
+    Registration { id }
+}
+
+impl Registration {
+    /// Runs `f` as the event loop of the program. The event loop is not bound to
+    /// a thread, since it may be a task moved between the workers of a runtime.
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        let _running = Running(RUNNING.replace(Some(self.id)));
+        f()
+    }
+}
+
+/// Restores the program running on the thread, even if the event loop panics.
+struct Running(Option<u64>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.set(self.0);
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        TERMINALS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|t| t.id != self.id);
+    }
+}
+
+/// Runs `f` and returns the panic message if it panics.
+/// When `silent`, the panic hook is not called. Otherwise the panic is
+/// expected to quit the program, so the terminals are restored.
+pub fn catch<T>(silent: bool, f: impl FnOnce() -> T) -> Result<T, String> {
+    install_hook();
+    let prev = CATCHING.replace(if silent {
+        Catching::Silent
+    } else {
+        Catching::Fatal
+    });
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.set(prev);
+    result.map_err(message)
+}
+
+fn message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        String::from("Box<dyn Any>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        panic,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    use super::{catch, register};
+
+    #[test]
+    fn catch_panic_message() {
+        assert_eq!(catch(true, || 1), Ok(1));
+        let n = 3;
+        assert_eq!(
+            catch(true, || -> i32 { panic!("failed at {}", n) }),
+            Err(String::from("failed at 3"))
+        );
+    }
+
+    #[test]
+    fn restore_on_fatal_panics() {
+        let restored = Arc::new(AtomicBool::new(false));
+        let flag = restored.clone();
+        let registration = register(move || flag.store(true, Ordering::SeqCst));
+        // a panic outside of the event loop, which may be recovered from
+        assert!(panic::catch_unwind(|| panic!("user")).is_err());
+        assert!(!restored.load(Ordering::SeqCst));
+        // the panic of the event loop
+        let event_loop = || registration.run(|| panic!("event loop"));
+        assert!(panic::catch_unwind(panic::AssertUnwindSafe(event_loop)).is_err());
+        assert!(restored.swap(false, Ordering::SeqCst));
+        // the panic of a command quitting the program
+        let command = std::thread::spawn(|| catch(false, || panic!("command")));
+        assert!(command.join().unwrap().is_err());
+        assert!(restored.load(Ordering::SeqCst));
+        drop(registration);
+    }
+}
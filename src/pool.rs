@@ -0,0 +1,171 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::{self, JoinHandle, ThreadId},
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Bounded set of worker threads which run the blocking commands of a program.
+/// Workers are started on demand, and exit once the pool is shut down, or every clone
+/// of it is dropped and the queued jobs are done.
+#[derive(Clone)]
+pub struct Pool {
+    handle: Arc<Handle>,
+}
+
+// dropped with the last clone of `Pool`, while workers only hold `Shared`
+struct Handle(Arc<Shared>);
+
+struct Shared {
+    state: Mutex<State>,
+    cond: Condvar,
+    max: usize,
+}
+
+#[derive(Default)]
+struct State {
+    jobs: VecDeque<Job>,
+    workers: Vec<JoinHandle<()>>,
+    // workers waiting for a job
+    idle: Vec<ThreadId>,
+    closed: bool,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.0.lock().closed = true;
+        self.0.cond.notify_all();
+    }
+}
+
+impl Pool {
+    /// Runs at most `max` jobs at once. Others wait in the queue.
+    pub fn new(max: usize) -> Pool {
+        let shared = Shared {
+            state: Mutex::new(State::default()),
+            cond: Condvar::new(),
+            max: max.max(1),
+        };
+        Pool {
+            handle: Arc::new(Handle(Arc::new(shared))),
+        }
+    }
+
+    /// Drops the jobs which have not started yet, and joins the idle workers.
+    /// Workers running a job exit once it is done, without being waited for.
+    pub fn shutdown(&self) {
+        let shared = &self.handle.0;
+        let (jobs, idle) = {
+            let mut state = shared.lock();
+            state.closed = true;
+            let (idle, busy) = std::mem::take(&mut state.workers)
+                .into_iter()
+                .partition(|w| state.idle.contains(&w.thread().id()));
+            state.workers = busy;
+            (std::mem::take(&mut state.jobs), idle)
+        };
+        shared.cond.notify_all();
+        // dropped after unlocking, since they may hold the last clone of the pool
+        drop(jobs);
+        for worker in idle {
+            let _ = worker.join();
+        }
+    }
+
+    /// Queues the job. It is dropped if the pool is shut down.
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        let shared = &self.handle.0;
+        let mut state = shared.lock();
+        if state.closed {
+            return;
+        }
+        state.jobs.push_back(Box::new(job));
+        if state.idle.len() < state.jobs.len() && state.workers.len() < shared.max {
+            let shared = shared.clone();
+            let worker = thread::spawn(move || work(&shared));
+            state.workers.push(worker);
+        } else {
+            shared.cond.notify_one();
+        }
+    }
+}
+
+fn work(shared: &Shared) {
+    let mut state = shared.lock();
+    loop {
+        if let Some(job) = state.jobs.pop_front() {
+            drop(state);
+            job();
+            state = shared.lock();
+        } else if state.closed {
+            return;
+        } else {
+            let id = thread::current().id();
+            state.idle.push(id);
+            state = shared.cond.wait(state).unwrap_or_else(|e| e.into_inner());
+            state.idle.retain(|idle| *idle != id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::channel,
+        Arc, Barrier,
+    };
+
+    use super::Pool;
+
+    #[test]
+    fn runs_at_most_max_jobs() {
+        let pool = Pool::new(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        // jobs wait for each other in pairs, so two of them always run at once
+        let pair = Arc::new(Barrier::new(2));
+        let (tx, rx) = channel();
+        for _ in 0..6 {
+            let (running, peak, pair, tx) =
+                (running.clone(), peak.clone(), pair.clone(), tx.clone());
+            pool.execute(move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                pair.wait();
+                running.fetch_sub(1, Ordering::SeqCst);
+                tx.send(()).unwrap();
+            });
+        }
+        drop(pool);
+        assert_eq!(rx.iter().take(6).count(), 6);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn shutdown_joins_idle_workers() {
+        let pool = Pool::new(2);
+        let shared = pool.handle.0.clone();
+        let (gate_tx, gate_rx) = channel::<()>();
+        let (done_tx, done_rx) = channel();
+        pool.execute(move || {
+            let _ = gate_rx.recv();
+        });
+        pool.execute(move || done_tx.send(()).unwrap());
+        done_rx.recv().unwrap();
+        while shared.lock().idle.is_empty() {
+            std::thread::yield_now();
+        }
+        pool.shutdown();
+        // the idle worker is gone, while the busy one still holds the pool
+        assert_eq!(Arc::strong_count(&shared), 3);
+        drop(gate_tx);
+    }
+}
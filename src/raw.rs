@@ -5,19 +5,34 @@ use std::fs::File;
 use std::io::{self, Write};
 use std::ops;
 
+use nix::libc;
 use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg, Termios};
 use nix::unistd::isatty;
 use std::os::unix::io::{AsRawFd, RawFd};
 
 use crate::error::TermixError;
 
-pub fn get_tty() -> Box<File> {
+pub fn get_tty() -> Result<Box<File>, TermixError> {
     let tty_file = std::fs::OpenOptions::new()
         .read(true)
         .write(true)
         .open("/dev/tty")
-        .expect("Cannot get tty");
-    Box::new(tty_file)
+        .map_err(TermixError::Tty)?;
+    Ok(Box::new(tty_file))
+}
+
+nix::ioctl_read_bad!(tiocgwinsz, libc::TIOCGWINSZ, libc::winsize);
+
+/// Returns (cols, rows) of the terminal.
+pub fn terminal_size(fd: RawFd) -> Result<(u16, u16), TermixError> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    unsafe { tiocgwinsz(fd, &mut size)? };
+    Ok((size.ws_col, size.ws_row))
 }
 
 /// A terminal restorer, which keeps the previous state of the terminal, and restores it, when
@@ -26,7 +41,10 @@ pub fn get_tty() -> Box<File> {
 /// Restoring will entirely bring back the old TTY state.
 pub struct RawTerminal<W: Write + AsRawFd> {
     prev_ios: Termios,
+    raw_ios: Termios,
     output: W,
+    // false after `finish_raw`, so that dropping doesn't restore twice
+    raw: bool,
 }
 
 impl<W: Write + AsRawFd> RawTerminal<W> {
@@ -40,17 +58,27 @@ impl<W: Write + AsRawFd> RawTerminal<W> {
             .write_all(b"\x1b[?25h")
             .map_err(|_| TermixError::Write(String::from("Clearing stdout")))
     }
+    /// Enters the raw mode again after `finish_raw`.
+    pub fn resume_raw(&mut self) -> Result<(), TermixError> {
+        tcsetattr(self.output.as_raw_fd(), SetArg::TCSANOW, &self.raw_ios)?;
+        self.raw = true;
+        Ok(())
+    }
     pub fn finish_raw(&mut self) -> Result<(), TermixError> {
         // show cursor
         self.show_cursor()?;
-        let _ = tcsetattr(self.output.as_raw_fd(), SetArg::TCSANOW, &self.prev_ios);
+        self.output.flush()?;
+        tcsetattr(self.output.as_raw_fd(), SetArg::TCSANOW, &self.prev_ios)?;
+        self.raw = false;
         Ok(())
     }
 }
 
 impl<W: Write + AsRawFd> Drop for RawTerminal<W> {
     fn drop(&mut self) {
-        self.finish_raw().unwrap();
+        if self.raw {
+            let _ = self.finish_raw();
+        }
     }
 }
 
@@ -112,17 +140,19 @@ impl<W: Write + AsRawFd> IntoRawMode for W {
         }
 
         let prev_ios = tcgetattr(self.as_raw_fd()).map_err(nix_err_to_io_err)?;
-        let mut ios = prev_ios.clone();
+        let mut raw_ios = prev_ios.clone();
         // set raw mode
-        cfmakeraw(&mut ios);
+        cfmakeraw(&mut raw_ios);
         // enable output processing (so that '\n' will issue carriage return)
-        ios.output_flags |= OutputFlags::OPOST;
+        raw_ios.output_flags |= OutputFlags::OPOST;
 
-        tcsetattr(self.as_raw_fd(), SetArg::TCSANOW, &ios).map_err(nix_err_to_io_err)?;
+        tcsetattr(self.as_raw_fd(), SetArg::TCSANOW, &raw_ios).map_err(nix_err_to_io_err)?;
 
         Ok(RawTerminal {
             prev_ios,
+            raw_ios,
             output: self,
+            raw: true,
         })
     }
 }
@@ -1,60 +1,248 @@
 use std::{
+    os::fd::RawFd,
     sync::{
-        mpsc::{channel, Receiver, Sender},
-        Arc, Mutex,
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc, Mutex, TryLockError,
     },
-    thread,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use crate::output::Output;
+use nix::sys::signal::{raise, Signal};
+
+use crate::{error::TermixError, model::Control, options::ProgramOptions, output::Output};
 
 #[derive(Debug)]
 pub struct StandardRenderer {
     out: Arc<Mutex<Output>>,
+    // error occurred while flushing in the background
+    error: Arc<Mutex<Option<TermixError>>>,
+    // flushes the pending view once per frame
+    ticker: Option<Ticker>,
 }
 
-struct Notifier {}
-
 impl StandardRenderer {
-    pub fn new() -> StandardRenderer {
-        StandardRenderer {
-            out: Arc::new(Mutex::new(Output::new())),
+    pub fn start(options: &ProgramOptions) -> Result<StandardRenderer, TermixError> {
+        let out = Arc::new(Mutex::new(Output::new(options)?));
+        let error = Arc::new(Mutex::new(None));
+        let ticker = {
+            let (out, error) = (out.clone(), error.clone());
+            Ticker::start(Duration::from_secs(1) / options.fps, move || {
+                let Ok(mut o) = out.lock() else {
+                    return false;
+                };
+                if !o.dirty() {
+                    return true;
+                }
+                match o.flush() {
+                    Ok(()) => true,
+                    Err(err) => {
+                        if let Ok(mut e) = error.lock() {
+                            *e = Some(err);
+                        }
+                        false
+                    }
+                }
+            })
+        };
+        Ok(StandardRenderer {
+            out,
+            error,
+            ticker: Some(ticker),
+        })
+    }
+
+    /// Stops the ticker, so that nothing is flushed after the terminal is restored.
+    fn stop(&mut self) {
+        if let Some(ticker) = self.ticker.take() {
+            ticker.stop();
         }
     }
 
-    pub fn start(&mut self) {
-        let (tx, rx) = channel();
-        self.interval_refresh(tx);
-        let out = self.out.clone();
-        listen(out, rx);
+    pub fn quit(&mut self) -> Result<(), TermixError> {
+        self.stop();
+        let mut o = self
+            .out
+            .lock()
+            .map_err(|_| TermixError::Write(String::from("Quitting")))?;
+        o.quit()
     }
 
-    pub fn quit(&mut self) {
-        let mut o = self.out.lock().unwrap();
-        o.quit();
+    /// Same as `quit`, but the pending view is discarded.
+    pub fn kill(&mut self) -> Result<(), TermixError> {
+        self.stop();
+        let mut o = self
+            .out
+            .lock()
+            .map_err(|_| TermixError::Write(String::from("Killing")))?;
+        o.kill()
     }
 
-    fn interval_refresh(&self, tx: Sender<Notifier>) {
-        thread::spawn(move || loop {
-            let _ = tx.send(Notifier {});
-        });
+    /// Returns a function restoring the terminal from the panic hook.
+    /// It gives up if the output is locked, since the panicking thread may hold the lock.
+    pub fn panic_restore(&self) -> impl Fn() + Send + 'static {
+        let out = Arc::downgrade(&self.out);
+        let stop = self.ticker.as_ref().map(|ticker| ticker.stop.clone());
+        move || {
+            // the thread panicking may be the ticker itself, so it is not joined
+            if let Some(stop) = &stop {
+                let _ = stop.send(());
+            }
+            let Some(out) = out.upgrade() else {
+                return;
+            };
+            let mut o = match out.try_lock() {
+                Ok(o) => o,
+                Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                Err(TryLockError::WouldBlock) => return,
+            };
+            let _ = o.kill();
+        }
     }
 
-    pub fn write(&mut self, new_data: &str) {
-        let out = self.out.clone();
-        let out = out.lock();
-        if let Ok(mut o) = out {
+    /// Applies the command handled by the program itself.
+    pub fn control(&mut self, control: Control) -> Result<(), TermixError> {
+        let mut o = self
+            .out
+            .lock()
+            .map_err(|_| TermixError::Write(String::from("Control")))?;
+        match control {
+            Control::EnterAltScreen => o.enter_alt_screen(),
+            Control::ExitAltScreen => o.exit_alt_screen(),
+            Control::Print(line) => {
+                o.print(&line);
+                Ok(())
+            }
+            Control::Suspend => {
+                o.suspend()?;
+                // the process stops here until it is continued by SIGCONT.
+                // the lock is held meanwhile so that nothing is flushed to the shell.
+                // SIGTSTP is handled by the program, so SIGSTOP is raised instead
+                raise(Signal::SIGSTOP)?;
+                o.resume()
+            }
+        }
+    }
+
+    /// Takes the terminal back after the process was stopped by someone else,
+    /// and repaints the view.
+    pub fn resume(&mut self) -> Result<(), TermixError> {
+        let mut o = self
+            .out
+            .lock()
+            .map_err(|_| TermixError::Write(String::from("Resume")))?;
+        o.resume()
+    }
+
+    /// Hands the terminal over to `f`, e.g. to run an interactive process,
+    /// then takes it back and repaints the view.
+    pub fn release<T>(&mut self, f: impl FnOnce() -> T) -> Result<T, TermixError> {
+        let mut o = self
+            .out
+            .lock()
+            .map_err(|_| TermixError::Write(String::from("Release")))?;
+        o.suspend()?;
+        let ret = f();
+        o.resume()?;
+        Ok(ret)
+    }
+
+    /// File descriptor of the terminal the view is drawn to.
+    pub fn raw_fd(&self) -> Result<RawFd, TermixError> {
+        let o = self
+            .out
+            .lock()
+            .map_err(|_| TermixError::Write(String::from("Raw fd")))?;
+        Ok(o.raw_fd())
+    }
+
+    pub fn resize(&mut self, cols: u16) {
+        if let Ok(mut o) = self.out.lock() {
+            o.resize(cols);
+        }
+    }
+
+    /// Queues the view to be flushed and reports the error of the last flush, if any.
+    pub fn write(&mut self, new_data: &str) -> Result<(), TermixError> {
+        if let Some(err) = self.error.lock().ok().and_then(|mut e| e.take()) {
+            return Err(err);
+        }
+        if let Ok(mut o) = self.out.lock() {
             o.write(new_data);
         }
+        Ok(())
+    }
+}
+
+impl Drop for StandardRenderer {
+    fn drop(&mut self) {
+        self.stop();
     }
 }
 
-fn listen(out: Arc<Mutex<Output>>, rx: Receiver<Notifier>) {
-    thread::spawn(move || {
-        while rx.recv().is_ok() {
-            if let Ok(mut o) = out.lock() {
-                o.flush();
+/// Thread calling `frame` at a fixed interval until it is stopped or `frame` returns false.
+/// Frames keep a fixed schedule, and the ones missed by a slow frame are skipped.
+#[derive(Debug)]
+struct Ticker {
+    stop: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl Ticker {
+    fn start(interval: Duration, mut frame: impl FnMut() -> bool + Send + 'static) -> Ticker {
+        let (stop, stop_rx) = channel();
+        let handle = thread::spawn(move || {
+            let mut next = Instant::now() + interval;
+            loop {
+                let wait = next.saturating_duration_since(Instant::now());
+                match stop_rx.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => break,
+                }
+                if !frame() {
+                    break;
+                }
+                next = (next + interval).max(Instant::now());
             }
-        }
-    });
+        });
+        Ticker { stop, handle }
+    }
+
+    /// Stops the thread and waits for the frame in progress.
+    fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.handle.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc::channel,
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::Ticker;
+
+    #[test]
+    fn ticker_stops_cleanly() {
+        let frames = Arc::new(AtomicUsize::new(0));
+        let counter = frames.clone();
+        let (tx, rx) = channel();
+        let ticker = Ticker::start(Duration::from_millis(1), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let _ = tx.send(());
+            true
+        });
+        rx.iter().take(3).for_each(drop);
+        ticker.stop();
+        let stopped = frames.load(Ordering::SeqCst);
+        // the frame is dropped with the joined thread, so nothing follows
+        assert_eq!(3 + rx.iter().count(), stopped);
+        assert_eq!(frames.load(Ordering::SeqCst), stopped);
+    }
 }
@@ -0,0 +1,183 @@
+//! Process-wide signal handling.
+//!
+//! Signal handlers only write the signal number to a pipe (the self-pipe trick).
+//! A single dispatcher thread reads the pipe and calls the callbacks of the
+//! programs subscribing the signal, so several programs can share the handlers.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    os::fd::{FromRawFd, RawFd},
+    sync::{
+        atomic::{AtomicI32, AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    thread,
+};
+
+use nix::{
+    libc,
+    sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal},
+};
+
+use crate::error::TermixError;
+
+type Callback = Box<dyn Fn(Signal) + Send + 'static>;
+
+struct Subscriber {
+    id: u64,
+    signals: Vec<Signal>,
+    callback: Callback,
+}
+
+#[derive(Default)]
+struct Registry {
+    subscribers: Vec<Subscriber>,
+    // previous dispositions, restored when nobody subscribes the signal anymore
+    installed: HashMap<Signal, SigAction>,
+}
+
+static WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+
+extern "C" fn handle(signo: libc::c_int) {
+    let fd = WRITE_FD.load(Ordering::Relaxed);
+    let byte = signo as u8;
+    // only async-signal-safe calls are allowed here
+    unsafe {
+        libc::write(fd, &byte as *const u8 as *const libc::c_void, 1);
+    }
+}
+
+fn registry() -> Result<&'static Mutex<Registry>, TermixError> {
+    if let Some(registry) = REGISTRY.get() {
+        return Ok(registry);
+    }
+    let (rx, tx) = nix::unistd::pipe()?;
+    let mut registered = false;
+    let registry = REGISTRY.get_or_init(|| {
+        registered = true;
+        Mutex::new(Registry::default())
+    });
+    if !registered {
+        // another thread won the race
+        let _ = nix::unistd::close(rx);
+        let _ = nix::unistd::close(tx);
+        return Ok(registry);
+    }
+    WRITE_FD.store(tx, Ordering::Relaxed);
+    thread::spawn(move || dispatch(rx));
+    Ok(registry)
+}
+
+fn dispatch(rx: RawFd) {
+    let mut pipe = unsafe { File::from_raw_fd(rx) };
+    let mut buf = [0u8; 1];
+    loop {
+        match pipe.read(&mut buf) {
+            Ok(1) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            _ => break,
+        }
+        let Ok(signal) = Signal::try_from(buf[0] as i32) else {
+            continue;
+        };
+        let Some(registry) = REGISTRY.get() else {
+            continue;
+        };
+        if let Ok(registry) = registry.lock() {
+            registry
+                .subscribers
+                .iter()
+                .filter(|s| s.signals.contains(&signal))
+                .for_each(|s| (s.callback)(signal));
+        }
+    }
+}
+
+/// Unsubscribes the signals when dropped.
+pub struct Subscription {
+    id: u64,
+}
+
+/// Calls `callback` on the dispatcher thread every time one of `signals` is received.
+pub fn subscribe<F>(signals: &[Signal], callback: F) -> Result<Subscription, TermixError>
+where
+    F: Fn(Signal) + Send + 'static,
+{
+    let registry = registry()?;
+    let mut registry = registry
+        .lock()
+        .map_err(|_| TermixError::Any(anyhow::anyhow!("signal registry is poisoned")))?;
+    for signal in signals {
+        if registry.installed.contains_key(signal) {
+            continue;
+        }
+        let action = SigAction::new(
+            SigHandler::Handler(handle),
+            SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        let prev = unsafe { sigaction(*signal, &action)? };
+        registry.installed.insert(*signal, prev);
+    }
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    registry.subscribers.push(Subscriber {
+        id,
+        signals: signals.to_vec(),
+        callback: Box::new(callback),
+    });
+    Ok(Subscription { id })
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let Some(registry) = REGISTRY.get() else {
+            return;
+        };
+        let Ok(mut registry) = registry.lock() else {
+            return;
+        };
+        registry.subscribers.retain(|s| s.id != self.id);
+        let unused: Vec<Signal> = registry
+            .installed
+            .keys()
+            .filter(|signal| {
+                !registry
+                    .subscribers
+                    .iter()
+                    .any(|s| s.signals.contains(signal))
+            })
+            .copied()
+            .collect();
+        for signal in unused {
+            if let Some(prev) = registry.installed.remove(&signal) {
+                let _ = unsafe { sigaction(signal, &prev) };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc::channel, time::Duration};
+
+    use nix::sys::signal::{raise, Signal};
+
+    use super::subscribe;
+
+    #[test]
+    fn dispatch_to_subscribers() {
+        let (tx, rx) = channel();
+        let subscription = subscribe(&[Signal::SIGUSR1], move |sig| {
+            let _ = tx.send(sig);
+        })
+        .unwrap();
+        raise(Signal::SIGUSR1).unwrap();
+        let received = rx.recv_timeout(Duration::from_secs(1));
+        assert_eq!(received, Ok(Signal::SIGUSR1));
+        drop(subscription);
+    }
+}
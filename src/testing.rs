@@ -0,0 +1,931 @@
+//! Headless harness to test models without a terminal.
+//!
+//! `TestProgram` drives a model like `Program` does, but events are scripted
+//! and commands run on the calling thread in a deterministic order.
+//! Ticks are fired by advancing a virtual clock instead of waiting.
+//!
+//! ```
+//! use std::time::Duration;
+//! use termix::{event::Event, key::Key, model::{Cmd, Model}, testing::TestProgram};
+//!
+//! struct Countdown(u32);
+//!
+//! impl Model<()> for Countdown {
+//!     fn update(&mut self, event: &Event<()>) -> Option<Cmd<()>> {
+//!         match event {
+//!             Event::Custom(()) if self.0 > 0 => {
+//!                 self.0 -= 1;
+//!                 Some(Cmd::tick(Duration::from_secs(1), |_| Event::Custom(())))
+//!             }
+//!             Event::Keyboard(Key::Char('q')) => Some(Cmd::quit()),
+//!             _ => None,
+//!         }
+//!     }
+//!     fn view(&self) -> String {
+//!         format!("{} seconds left\n", self.0)
+//!     }
+//! }
+//!
+//! let mut program = TestProgram::new(Countdown(3));
+//! program.send(());
+//! assert_eq!(program.last_frame(), "2 seconds left\n");
+//! program.advance(Duration::from_secs(2));
+//! assert_eq!(program.last_frame(), "0 seconds left\n");
+//! program.key(Key::Char('q'));
+//! assert!(program.has_quit());
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    env,
+    fmt::Debug,
+    fs, io,
+    os::unix::process::ExitStatusExt,
+    path::Path,
+    process::{Command, ExitStatus},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    color::{Ansi16Value, Color, StyledText},
+    dispatch::{Dispatcher, Filter, Flow, Host},
+    error::TermixError,
+    event::{Event, Message},
+    executor,
+    key::Key,
+    model::{Cmd, CmdKind, Control, Model, Scope},
+    options::ProgramOptions,
+    output::strip_ansi,
+};
+
+/// Continuation called when a command has fired all of its events.
+type Done<E> = Box<dyn FnOnce(&mut Sim<E>)>;
+
+struct Timer<E: Send + Debug> {
+    // virtual time since the program was created
+    at: Duration,
+    seq: u64,
+    f: Box<dyn FnOnce(Instant) -> Event<E> + Send>,
+    scope: Scope,
+    done: Done<E>,
+}
+
+/// Drives a model with scripted events and records the rendered frames.
+///
+/// Messages are handled by the same code as `Program`. Commands run
+/// synchronously: closures are called right away, batches run their commands
+/// in order, and ticks wait for `advance`.
+/// `Cmd::exec` doesn't run the process; it is recorded, and the callback
+/// receives the status set by `exec_status`.
+pub struct TestProgram<M, E: Send + Debug> {
+    dispatcher: Dispatcher<M, E>,
+    sim: Sim<E>,
+}
+
+/// Terminal and executor of `TestProgram`.
+struct Sim<E: Send + Debug> {
+    queue: VecDeque<Message<E>>,
+    // continuations of the `Cmd::exec`s in the queue, in order
+    exec_done: VecDeque<Done<E>>,
+    executed: Vec<Command>,
+    exec_status: ExitStatus,
+    frames: Vec<String>,
+    printed: Vec<String>,
+    alt_screen: bool,
+    quit: bool,
+    // virtual clock
+    start: Instant,
+    now: Duration,
+    timers: Vec<Timer<E>>,
+    next_seq: u64,
+}
+
+impl<M: Model<E> + 'static, E: Send + Debug + 'static> TestProgram<M, E> {
+    /// Starts the model in a 80x24 terminal.
+    pub fn new(model: M) -> TestProgram<M, E> {
+        TestProgram::with_options(model, ProgramOptions::default())
+    }
+
+    /// Starts the model in a 80x24 terminal. Key bindings of the options are applied.
+    pub fn with_options(model: M, options: ProgramOptions) -> TestProgram<M, E> {
+        TestProgram::builder(model).options(options).start()
+    }
+
+    /// Configures the program before it starts, e.g. to add filters like `Program::filter`.
+    pub fn builder(model: M) -> TestProgramBuilder<M, E> {
+        TestProgramBuilder {
+            model,
+            options: ProgramOptions::default(),
+            filters: vec![],
+        }
+    }
+
+    fn start(builder: TestProgramBuilder<M, E>) -> TestProgram<M, E> {
+        let mut dispatcher = Dispatcher::new(builder.model, &builder.options);
+        dispatcher.filters = builder.filters;
+        let mut program = TestProgram {
+            dispatcher,
+            sim: Sim {
+                queue: VecDeque::new(),
+                exec_done: VecDeque::new(),
+                executed: vec![],
+                exec_status: ExitStatus::from_raw(0),
+                frames: vec![],
+                printed: vec![],
+                alt_screen: builder.options.alt_screen,
+                quit: false,
+                start: Instant::now(),
+                now: Duration::ZERO,
+                timers: vec![],
+                next_seq: 0,
+            },
+        };
+        program.sim.queue.push_back(Message::Event(Event::Init));
+        program
+            .sim
+            .queue
+            .push_back(Message::Event(Event::Resize { cols: 80, rows: 24 }));
+        program.check(|p| p.dispatcher.start(&mut p.sim));
+        program.process();
+        program
+    }
+
+    pub fn key(&mut self, key: Key) {
+        self.event(Event::Keyboard(key));
+    }
+
+    /// Types the characters of `text` as `Key::Char`s.
+    pub fn type_text(&mut self, text: &str) {
+        text.chars().for_each(|c| self.key(Key::Char(c)));
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        self.event(Event::Resize { cols, rows });
+    }
+
+    /// Fires `Event::Custom`, like `ProgramHandle::send`.
+    pub fn send(&mut self, event: E) {
+        self.event(Event::Custom(event));
+    }
+
+    /// Fires the event and processes everything it causes, except the ticks.
+    pub fn event(&mut self, event: Event<E>) {
+        self.sim.queue.push_back(Message::Event(event));
+        self.process();
+    }
+
+    /// Moves the virtual clock forward, firing the ticks due in order.
+    pub fn advance(&mut self, duration: Duration) {
+        let until = self.sim.now + duration;
+        while let Some(i) = self.sim.next_timer(until) {
+            let timer = self.sim.timers.swap_remove(i);
+            self.sim.now = timer.at;
+            if !timer.scope.is_cancelled() {
+                let ev = (timer.f)(self.sim.start + timer.at);
+                self.sim.push(ev, &timer.scope);
+            }
+            (timer.done)(&mut self.sim);
+            self.process();
+        }
+        self.sim.now = until;
+    }
+
+    /// Exit status passed to the callbacks of `Cmd::exec` from now on. Defaults to success.
+    pub fn exec_status(&mut self, status: ExitStatus) {
+        self.sim.exec_status = status;
+    }
+
+    /// Processes started by `Cmd::exec`, in order.
+    pub fn executed(&self) -> &[Command] {
+        &self.sim.executed
+    }
+
+    /// Every frame rendered so far, starting with the initial view.
+    pub fn frames(&self) -> &[String] {
+        &self.sim.frames
+    }
+
+    pub fn last_frame(&self) -> &str {
+        self.sim
+            .frames
+            .last()
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+
+    /// Lines printed by `Cmd::println` while not in the alternate screen.
+    pub fn printed(&self) -> &[String] {
+        &self.sim.printed
+    }
+
+    pub fn alt_screen(&self) -> bool {
+        self.sim.alt_screen
+    }
+
+    /// Whether the program has quit. Events fired after quitting are ignored.
+    pub fn has_quit(&self) -> bool {
+        self.sim.quit
+    }
+
+    /// Number of ticks waiting for the clock.
+    pub fn pending_ticks(&self) -> usize {
+        self.sim.timers.len()
+    }
+
+    pub fn model(&self) -> &M {
+        &self.dispatcher.model
+    }
+
+    pub fn model_mut(&mut self) -> &mut M {
+        &mut self.dispatcher.model
+    }
+
+    pub fn into_model(self) -> M {
+        self.dispatcher.model
+    }
+
+    fn process(&mut self) {
+        while let Some(msg) = self.sim.queue.pop_front() {
+            if self.sim.quit {
+                self.sim.queue.clear();
+                return;
+            }
+            let exec = matches!(msg, Message::Exec(..));
+            let flow = self.check(|p| p.dispatcher.dispatch(msg, &mut p.sim));
+            if exec {
+                // the command is continued once the event of the callback has been handled
+                if let Some(done) = self.sim.exec_done.pop_front() {
+                    done(&mut self.sim);
+                }
+            }
+            self.sim.quit = flow == Flow::Quit;
+        }
+    }
+
+    // the program fails only by a message which is never sent in the harness
+    fn check<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, TermixError>) -> T {
+        f(self).unwrap_or_else(|err| panic!("program failed: {err}"))
+    }
+}
+
+impl<E: Send + Debug + 'static> Sim<E> {
+    fn next_timer(&self, until: Duration) -> Option<usize> {
+        self.timers
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.at <= until)
+            .min_by_key(|(_, t)| (t.at, t.seq))
+            .map(|(i, _)| i)
+    }
+
+    fn push(&mut self, event: Event<E>, scope: &Scope) {
+        self.queue.push_back(Message::Scoped(scope.clone(), event));
+    }
+
+    fn execute(&mut self, cmd: Cmd<E>, scope: Scope, done: Done<E>) {
+        if scope.is_cancelled() {
+            return done(self);
+        }
+        match cmd.kind {
+            CmdKind::Func(f) => {
+                self.push(f(), &scope);
+                done(self);
+            }
+            CmdKind::Batch(cmds) if cmds.is_empty() => done(self),
+            CmdKind::Batch(cmds) => {
+                // the last command to finish calls `done`
+                let left = Rc::new(RefCell::new((cmds.len(), Some(done))));
+                for cmd in cmds {
+                    let left = left.clone();
+                    self.execute(
+                        cmd,
+                        scope.clone(),
+                        Box::new(move |sim| {
+                            let mut left = left.borrow_mut();
+                            left.0 -= 1;
+                            if left.0 > 0 {
+                                return;
+                            }
+                            if let Some(done) = left.1.take() {
+                                drop(left);
+                                done(sim);
+                            }
+                        }),
+                    );
+                }
+            }
+            CmdKind::Sequence(cmds) => self.sequence(cmds.into_iter().collect(), scope, done),
+            CmdKind::Tick {
+                duration,
+                aligned,
+                f,
+            } => {
+                let at = if aligned && !duration.is_zero() {
+                    let interval = duration.as_nanos();
+                    let next = (self.now.as_nanos() / interval + 1) * interval;
+                    Duration::from_nanos(next as u64)
+                } else {
+                    self.now + duration
+                };
+                let seq = self.next_seq;
+                self.next_seq += 1;
+                self.timers.push(Timer {
+                    at,
+                    seq,
+                    f,
+                    scope,
+                    done,
+                });
+            }
+            CmdKind::Control(control) => {
+                self.queue.push_back(Message::Control(control));
+                done(self);
+            }
+            CmdKind::Exec(command, callback) => {
+                let msg = executor::exec(command, callback, scope, Box::new(|| {}));
+                self.queue.push_back(msg);
+                self.exec_done.push_back(done);
+            }
+            CmdKind::Cancellable(token, cmd) => self.execute(*cmd, scope.with(token), done),
+            #[cfg(feature = "tokio")]
+            CmdKind::Future(future) => {
+                let ev = block_on(future);
+                self.push(ev, &scope);
+                done(self);
+            }
+        }
+    }
+
+    fn sequence(&mut self, mut cmds: VecDeque<Cmd<E>>, scope: Scope, done: Done<E>) {
+        match cmds.pop_front() {
+            Some(cmd) => {
+                let next = scope.clone();
+                self.execute(
+                    cmd,
+                    scope,
+                    Box::new(move |sim| sim.sequence(cmds, next, done)),
+                )
+            }
+            None => done(self),
+        }
+    }
+}
+
+/// Awaits a future command. In a test run by a runtime, e.g. `#[tokio::test]`, the future
+/// is awaited on that runtime, unless it is a current_thread one, which can't be blocked.
+#[cfg(feature = "tokio")]
+fn block_on<T: Send>(future: impl std::future::Future<Output = T> + Send) -> T {
+    use tokio::runtime::{Builder, Handle, RuntimeFlavor};
+
+    let own = || {
+        Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to start tokio runtime")
+    };
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| handle.block_on(future))
+        }
+        // a runtime can't be started on a thread which runs another one
+        Ok(_) => std::thread::scope(|s| {
+            s.spawn(|| own().block_on(future))
+                .join()
+                .unwrap_or_else(|e| std::panic::resume_unwind(e))
+        }),
+        Err(_) => own().block_on(future),
+    }
+}
+
+impl<E: Send + Debug + 'static> Host<E> for Sim<E> {
+    fn suspend(&mut self) -> Result<(), TermixError> {
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), TermixError> {
+        Ok(())
+    }
+
+    fn control(&mut self, control: Control) -> Result<(), TermixError> {
+        match control {
+            Control::EnterAltScreen => self.alt_screen = true,
+            Control::ExitAltScreen => self.alt_screen = false,
+            Control::Print(line) if !self.alt_screen => self.printed.push(line),
+            Control::Print(_) | Control::Suspend => {}
+        }
+        Ok(())
+    }
+
+    fn exec(&mut self, command: Command) -> Result<io::Result<ExitStatus>, TermixError> {
+        self.executed.push(command);
+        Ok(Ok(self.exec_status))
+    }
+
+    fn resize(&mut self, _: u16) {}
+
+    fn render(&mut self, view: String) -> Result<(), TermixError> {
+        self.frames.push(view);
+        Ok(())
+    }
+
+    fn spawn(&mut self, cmd: Cmd<E>) {
+        self.execute(cmd, Scope::default(), Box::new(|_| {}));
+    }
+}
+
+/// Builder of `TestProgram`, made by `TestProgram::builder`.
+pub struct TestProgramBuilder<M, E: Send + Debug> {
+    model: M,
+    options: ProgramOptions,
+    filters: Vec<Filter<M, E>>,
+}
+
+impl<M: Model<E> + 'static, E: Send + Debug + 'static> TestProgramBuilder<M, E> {
+    pub fn options(mut self, options: ProgramOptions) -> TestProgramBuilder<M, E> {
+        self.options = options;
+        self
+    }
+
+    /// Same as `Program::filter`.
+    pub fn filter<F>(mut self, f: F) -> TestProgramBuilder<M, E>
+    where
+        F: Fn(&M, Event<E>) -> Option<Event<E>> + Send + 'static,
+    {
+        self.filters.push(Box::new(f));
+        self
+    }
+
+    /// Starts the model in a 80x24 terminal.
+    pub fn start(self) -> TestProgram<M, E> {
+        TestProgram::start(self)
+    }
+}
+
+/// How escape sequences such as SGR are stored in snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sgr {
+    /// Only the text is compared.
+    #[default]
+    Strip,
+    /// Styles are compared too. ESC is written as `\x1b` to keep the file readable.
+    Keep,
+}
+
+/// Compares the frame with the snapshot stored under `tests/snapshots/` of the crate.
+///
+/// On mismatch, it panics with a diff. A missing snapshot fails as well, and the frame
+/// is written to `<name>.snap.new` for review. With `TERMIX_UPDATE_SNAPSHOTS=1`,
+/// snapshots are created or overwritten instead of compared.
+/// Snapshots are named after the test function, numbered if it asserts several frames.
+///
+/// ```no_run
+/// use termix::{assert_frame_snapshot, testing::{Sgr, TestProgram}};
+/// # use termix::{event::Event, model::{Cmd, Model}};
+/// # struct Menu;
+/// # impl Model<()> for Menu {
+/// #     fn update(&mut self, _: &Event<()>) -> Option<Cmd<()>> { None }
+/// #     fn view(&self) -> String { String::new() }
+/// # }
+///
+/// let program = TestProgram::new(Menu);
+/// assert_frame_snapshot!(program.last_frame());
+/// assert_frame_snapshot!(program.last_frame(), Sgr::Keep);
+/// ```
+#[macro_export]
+macro_rules! assert_frame_snapshot {
+    ($frame:expr) => {
+        $crate::assert_frame_snapshot!($frame, $crate::testing::Sgr::Strip)
+    };
+    ($frame:expr, $sgr:expr) => {{
+        fn f() {}
+        let name = $crate::testing::snapshot_name(::std::any::type_name_of_val(&f));
+        let dir = ::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+        $crate::testing::assert_snapshot(&dir, &name, &$frame, $sgr);
+    }};
+}
+
+thread_local! {
+    static SNAPSHOT_COUNTS: RefCell<HashMap<String, usize>> = RefCell::new(HashMap::new());
+}
+
+/// Makes the snapshot name from the path of a function defined in the test.
+#[doc(hidden)]
+pub fn snapshot_name(fn_path: &str) -> String {
+    let name = fn_path
+        .split("::")
+        .skip(1)
+        .filter(|s| *s != "f" && *s != "{{closure}}")
+        .collect::<Vec<_>>()
+        .join("__");
+    // each test runs on its own thread
+    let n = SNAPSHOT_COUNTS.with_borrow_mut(|counts| {
+        let n = counts.entry(name.clone()).or_insert(0);
+        *n += 1;
+        *n
+    });
+    if n == 1 {
+        name
+    } else {
+        format!("{name}-{n}")
+    }
+}
+
+#[doc(hidden)]
+pub fn assert_snapshot(dir: &Path, name: &str, frame: &str, sgr: Sgr) {
+    let update = env::var("TERMIX_UPDATE_SNAPSHOTS").is_ok_and(|v| v == "1");
+    check_snapshot(dir, name, frame, sgr, update);
+}
+
+fn check_snapshot(dir: &Path, name: &str, frame: &str, sgr: Sgr, update: bool) {
+    let actual = match sgr {
+        Sgr::Strip => strip_ansi(frame),
+        Sgr::Keep => frame.replace('\x1b', "\\x1b"),
+    };
+    let path = dir.join(format!("{name}.snap"));
+    let new = dir.join(format!("{name}.snap.new"));
+    let write = |path: &Path| {
+        fs::create_dir_all(dir)
+            .and_then(|_| fs::write(path, &actual))
+            .unwrap_or_else(|e| panic!("failed to write snapshot {}: {e}", path.display()));
+    };
+    if update {
+        write(&path);
+        let _ = fs::remove_file(&new);
+        return;
+    }
+    match fs::read_to_string(&path) {
+        Ok(expected) if expected == actual => {
+            let _ = fs::remove_file(&new);
+        }
+        Ok(expected) => panic!(
+            "frame does not match snapshot {}\n{}\nrun with TERMIX_UPDATE_SNAPSHOTS=1 to update it",
+            path.display(),
+            diff(&expected, &actual)
+        ),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            write(&new);
+            panic!(
+                "snapshot {} is missing, the frame is written to {}\nrun with TERMIX_UPDATE_SNAPSHOTS=1 to create it",
+                path.display(),
+                new.display()
+            );
+        }
+        Err(e) => panic!("failed to read snapshot {}: {e}", path.display()),
+    }
+}
+
+/// Line diff of the snapshot and the frame, removed lines in red and added lines in green.
+fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.split('\n').collect();
+    let new: Vec<&str> = actual.split('\n').collect();
+    // lengths of the longest common subsequences of the suffixes
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let red = |line: &str| styled(&format!("-{line}"), Ansi16Value::Red);
+    let green = |line: &str| styled(&format!("+{line}"), Ansi16Value::Green);
+    let (mut i, mut j) = (0, 0);
+    let mut out = vec![];
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            out.push(format!(" {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            out.push(green(new[j]));
+            j += 1;
+        } else {
+            out.push(red(old[i]));
+            i += 1;
+        }
+    }
+    out.join("\n")
+}
+
+fn styled(text: &str, color: Ansi16Value) -> String {
+    StyledText::new(text, Some(Color::Ansi16(color)), None, None, None, None).text()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::unix::process::ExitStatusExt,
+        process::{Command, ExitStatus},
+        time::Duration,
+    };
+
+    use super::{check_snapshot, diff, snapshot_name, Sgr, TestProgram};
+    use crate::{
+        color::{Color, StyledText},
+        event::Event,
+        key::Key,
+        model::{CancellationToken, Cmd, Model, ModelAct, ModelActAdapter, Updater},
+        options::ProgramOptions,
+    };
+
+    struct Log(Vec<u32>);
+
+    impl Model<u32> for Log {
+        fn update(&mut self, event: &Event<u32>) -> Option<Cmd<u32>> {
+            match event {
+                Event::Keyboard(Key::Char('s')) => Some(Cmd::sequence(vec![
+                    Cmd::tick(Duration::from_secs(2), |_| Event::Custom(1)),
+                    Cmd::batch(vec![
+                        Cmd::tick(Duration::from_secs(2), |_| Event::Custom(3)),
+                        Cmd::tick(Duration::from_secs(1), |_| Event::Custom(2)),
+                    ]),
+                    Cmd::new(|| Event::Custom(4)),
+                    Cmd::println("done"),
+                ])),
+                Event::Custom(n) => {
+                    self.0.push(*n);
+                    None
+                }
+                _ => None,
+            }
+        }
+        fn view(&self) -> String {
+            format!("{:?}", self.0)
+        }
+    }
+
+    #[test]
+    fn ticks_follow_virtual_clock() {
+        let mut program = TestProgram::new(Log(vec![]));
+        program.key(Key::Char('s'));
+        program.advance(Duration::from_secs(1));
+        assert_eq!(program.last_frame(), "[]");
+        program.advance(Duration::from_secs(2));
+        assert_eq!(program.last_frame(), "[1, 2]");
+        program.advance(Duration::from_secs(1));
+        assert_eq!(program.last_frame(), "[1, 2, 3, 4]");
+        assert_eq!(program.printed(), ["done"]);
+        assert_eq!(program.pending_ticks(), 0);
+    }
+
+    // searches for the typed digit, restarting on every key
+    struct Search {
+        token: CancellationToken,
+        found: Vec<u32>,
+    }
+
+    impl Model<u32> for Search {
+        fn update(&mut self, event: &Event<u32>) -> Option<Cmd<u32>> {
+            match event {
+                Event::Keyboard(Key::Char(c)) => {
+                    self.token.cancel();
+                    self.token = CancellationToken::new();
+                    let n = c.to_digit(10)?;
+                    let search = Cmd::tick(Duration::from_secs(1), move |_| Event::Custom(n));
+                    Some(search.with_token(&self.token))
+                }
+                Event::Custom(n) => {
+                    self.found.push(*n);
+                    None
+                }
+                _ => None,
+            }
+        }
+        fn view(&self) -> String {
+            format!("{:?}", self.found)
+        }
+    }
+
+    #[test]
+    fn cancelled_ticks_are_dropped() {
+        let mut program = TestProgram::new(Search {
+            token: CancellationToken::new(),
+            found: vec![],
+        });
+        program.type_text("12");
+        program.advance(Duration::from_secs(1));
+        assert_eq!(program.last_frame(), "[2]");
+    }
+
+    struct Editor(Vec<u32>);
+
+    impl Model<u32> for Editor {
+        fn update(&mut self, event: &Event<u32>) -> Option<Cmd<u32>> {
+            match event {
+                Event::Keyboard(Key::Char('e')) => Some(Cmd::sequence(vec![
+                    Cmd::exec(Command::new("vim"), |status| {
+                        Event::Custom(status.map_or(0, |s| s.code().unwrap_or(0)) as u32)
+                    }),
+                    Cmd::new(|| Event::Custom(9)),
+                ])),
+                Event::Custom(n) => {
+                    self.0.push(*n);
+                    None
+                }
+                _ => None,
+            }
+        }
+        fn view(&self) -> String {
+            format!("{:?}", self.0)
+        }
+    }
+
+    #[test]
+    fn exec_is_recorded() {
+        let mut program = TestProgram::new(Editor(vec![]));
+        program.key(Key::Char('e'));
+        program.exec_status(ExitStatus::from_raw(3 << 8));
+        program.key(Key::Char('e'));
+        assert_eq!(program.last_frame(), "[0, 9, 3, 9]");
+        let executed: Vec<_> = program.executed().iter().map(|c| c.get_program()).collect();
+        assert_eq!(executed, ["vim", "vim"]);
+    }
+
+    #[test]
+    fn filters_keep_order() {
+        let mut program = TestProgram::builder(Log(vec![]))
+            .filter(|log: &Log, ev: Event<u32>| Some(ev.map(|n| n + log.0.len() as u32)))
+            .filter(|_, ev| match ev {
+                Event::Custom(n) if n > 10 => None,
+                ev => Some(ev),
+            })
+            .start();
+        program.send(1);
+        program.send(10);
+        program.send(5);
+        assert_eq!(program.last_frame(), "[1, 6]");
+    }
+
+    #[test]
+    fn snapshot_names_are_numbered() {
+        fn f() {}
+        let path = std::any::type_name_of_val(&f);
+        let name = snapshot_name(path);
+        assert_eq!(name, "testing__tests__snapshot_names_are_numbered");
+        assert_eq!(snapshot_name(path), format!("{name}-2"));
+    }
+
+    #[test]
+    fn snapshot_written_then_compared() {
+        let dir = std::env::temp_dir().join(format!("termix-snapshots-{}", std::process::id()));
+        let frame = StyledText::new("hi", Some(Color::Ansi256(212)), None, None, None, None).text();
+        let check = |name, frame: &str, sgr, update| {
+            std::panic::catch_unwind(|| check_snapshot(&dir, name, frame, sgr, update)).is_ok()
+        };
+        // missing: fails and writes the frame aside
+        assert!(!check("plain", &frame, Sgr::Strip, false));
+        assert!(!dir.join("plain.snap").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("plain.snap.new")).unwrap(),
+            "hi"
+        );
+        assert!(check("plain", &frame, Sgr::Strip, true));
+        assert!(!dir.join("plain.snap.new").exists());
+        assert!(check("plain", &frame, Sgr::Strip, false));
+        assert!(!check("plain", "ho", Sgr::Strip, false));
+        assert!(check("styled", &frame, Sgr::Keep, true));
+        assert!(check("styled", &frame, Sgr::Keep, false));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    // shows the help in the alternate screen until another key is pressed
+    struct Help;
+
+    impl Model<u32> for Help {
+        fn update(&mut self, event: &Event<u32>) -> Option<Cmd<u32>> {
+            match event {
+                Event::Keyboard(Key::Char('h')) => Some(Cmd::enter_alt_screen()),
+                Event::Keyboard(_) => Some(Cmd::exit_alt_screen()),
+                _ => None,
+            }
+        }
+        fn view(&self) -> String {
+            String::new()
+        }
+    }
+
+    #[test]
+    fn alt_screen_toggled_by_commands() {
+        let mut program = TestProgram::new(Help);
+        assert!(!program.alt_screen());
+        program.key(Key::Char('h'));
+        assert!(program.alt_screen());
+        program.key(Key::Char('q'));
+        assert!(!program.alt_screen());
+        let program = TestProgram::with_options(Help, ProgramOptions::new().alt_screen(true));
+        assert!(program.alt_screen());
+    }
+
+    // counts the keys and the times it came back from suspension
+    #[derive(Default)]
+    struct Shell {
+        keys: usize,
+        resumes: usize,
+    }
+
+    impl Model<u32> for Shell {
+        fn update(&mut self, event: &Event<u32>) -> Option<Cmd<u32>> {
+            match event {
+                Event::Keyboard(_) => self.keys += 1,
+                Event::Resume => self.resumes += 1,
+                _ => {}
+            }
+            None
+        }
+        fn view(&self) -> String {
+            String::new()
+        }
+    }
+
+    #[test]
+    fn ctrl_z_suspends_when_enabled() {
+        let options = ProgramOptions::new().suspend_on_ctrl_z(true);
+        let mut program = TestProgram::with_options(Shell::default(), options);
+        program.key(Key::Ctrl('z'));
+        assert_eq!((program.model().keys, program.model().resumes), (0, 1));
+        // otherwise, it is a key like the others
+        let mut program = TestProgram::new(Shell::default());
+        program.key(Key::Ctrl('z'));
+        assert_eq!((program.model().keys, program.model().resumes), (1, 0));
+    }
+
+    // loads its items with the command run by `init`
+    struct Loader(Option<u32>);
+
+    impl ModelAct<Loader, u32> for Loader {
+        fn update(&self, event: &Event<u32>) -> Updater<Loader, u32> {
+            match event {
+                Event::Custom(n) => (Some(Box::new(Loader(Some(*n)))), None),
+                _ => (None, None),
+            }
+        }
+        fn view(&self) -> String {
+            match self.0 {
+                Some(n) => format!("{n} items"),
+                None => String::from("loading"),
+            }
+        }
+        fn init(&self) -> Option<Cmd<u32>> {
+            Some(Cmd::new(|| Event::Custom(3)))
+        }
+    }
+
+    #[test]
+    fn init_runs_before_first_render() {
+        let program = TestProgram::new(ModelActAdapter::new(Box::new(Loader(None))));
+        assert_eq!(program.frames()[0], "loading");
+        assert_eq!(program.last_frame(), "3 items");
+    }
+
+    #[test]
+    fn diff_lines() {
+        let d = crate::output::strip_ansi(&diff("a\nb\nc", "a\nx\nc"));
+        assert_eq!(d, " a\n+x\n-b\n c");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn futures_awaited_in_any_runtime() {
+        use tokio::runtime::Builder;
+
+        struct Fetch(Option<u32>);
+
+        impl Model<u32> for Fetch {
+            fn update(&mut self, event: &Event<u32>) -> Option<Cmd<u32>> {
+                match event {
+                    Event::Keyboard(_) => Some(Cmd::future(async {
+                        tokio::time::sleep(Duration::from_millis(1)).await;
+                        Event::Custom(1)
+                    })),
+                    Event::Custom(n) => {
+                        self.0 = Some(*n);
+                        None
+                    }
+                    _ => None,
+                }
+            }
+            fn view(&self) -> String {
+                format!("{:?}", self.0)
+            }
+        }
+
+        let fetch = || {
+            let mut program = TestProgram::new(Fetch(None));
+            program.key(Key::Enter);
+            program.last_frame().to_string()
+        };
+        assert_eq!(fetch(), "Some(1)");
+        let current = Builder::new_current_thread().enable_all().build().unwrap();
+        assert_eq!(current.block_on(async { fetch() }), "Some(1)");
+        let multi = Builder::new_multi_thread().enable_all().build().unwrap();
+        assert_eq!(multi.block_on(async { fetch() }), "Some(1)");
+        assert_eq!(
+            multi.block_on(multi.spawn(async move { fetch() })).unwrap(),
+            "Some(1)"
+        );
+    }
+}
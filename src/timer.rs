@@ -0,0 +1,183 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+#[cfg(not(feature = "tokio"))]
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+#[cfg(not(feature = "tokio"))]
+type Fire = Box<dyn FnOnce(Instant) + Send + 'static>;
+
+#[cfg(not(feature = "tokio"))]
+enum Request {
+    Schedule(Instant, Fire),
+    Stop,
+}
+
+#[cfg(not(feature = "tokio"))]
+struct Entry {
+    at: Instant,
+    seq: u64,
+    fire: Fire,
+}
+
+#[cfg(not(feature = "tokio"))]
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.at == other.at && self.seq == other.seq
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl Eq for Entry {}
+
+#[cfg(not(feature = "tokio"))]
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl Ord for Entry {
+    // reversed, so that BinaryHeap pops the earliest deadline first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.at.cmp(&self.at).then(other.seq.cmp(&self.seq))
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+/// Single thread which fires all the scheduled timers of a program.
+#[derive(Clone)]
+pub struct Timer {
+    tx: Sender<Request>,
+    handle: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+#[cfg(not(feature = "tokio"))]
+impl Timer {
+    pub fn new() -> Timer {
+        let (tx, rx) = channel::<Request>();
+        let handle = thread::spawn(move || {
+            let mut queue = BinaryHeap::new();
+            let mut seq = 0;
+            let mut connected = true;
+            loop {
+                let wait = queue
+                    .peek()
+                    .map(|e: &Entry| e.at.saturating_duration_since(Instant::now()));
+                let received = match (wait, connected) {
+                    (None, false) => break,
+                    (None, true) => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    (Some(wait), true) => rx.recv_timeout(wait),
+                    // nobody can schedule anymore, but pending timers still fire
+                    (Some(wait), false) => {
+                        thread::sleep(wait);
+                        Err(RecvTimeoutError::Timeout)
+                    }
+                };
+                match received {
+                    Ok(Request::Schedule(at, fire)) => {
+                        seq += 1;
+                        queue.push(Entry { at, seq, fire });
+                    }
+                    // pending timers are dropped without firing
+                    Ok(Request::Stop) => break,
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => connected = false,
+                }
+                let now = Instant::now();
+                while queue.peek().is_some_and(|e| e.at <= now) {
+                    let entry = queue.pop().unwrap();
+                    (entry.fire)(now);
+                }
+            }
+        });
+        Timer {
+            tx,
+            handle: Arc::new(Mutex::new(Some(handle))),
+        }
+    }
+
+    /// Calls `fire` with the current instant once `at` has passed.
+    pub fn schedule<F>(&self, at: Instant, fire: F)
+    where
+        F: FnOnce(Instant) + Send + 'static,
+    {
+        let _ = self.tx.send(Request::Schedule(at, Box::new(fire)));
+    }
+
+    /// Stops the thread of every clone, dropping the pending timers, and waits for it.
+    pub fn stop(&self) {
+        let _ = self.tx.send(Request::Stop);
+        let handle = self.handle.lock().ok().and_then(|mut h| h.take());
+        if let Some(handle) = handle {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Returns the instant of the next wall-clock boundary of `interval`,
+/// e.g. the beginning of the next second for an interval of one second.
+pub fn next_boundary(interval: Duration) -> Instant {
+    let now = Instant::now();
+    let interval_nanos = interval.as_nanos();
+    if interval_nanos == 0 {
+        return now;
+    }
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let wait = interval_nanos - since_epoch % interval_nanos;
+    now + Duration::from_nanos(wait as u64)
+}
+
+#[cfg(all(test, not(feature = "tokio")))]
+mod tests {
+    use std::{
+        sync::mpsc::channel,
+        time::{Duration, Instant},
+    };
+
+    use super::Timer;
+
+    #[test]
+    fn fires_in_deadline_order() {
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        let now = Instant::now();
+        for n in [30, 10, 20] {
+            let tx = tx.clone();
+            timer.schedule(now + Duration::from_millis(n), move |at| {
+                tx.send((n, at)).unwrap();
+            });
+        }
+        drop(tx);
+        let fired: Vec<_> = rx.iter().collect();
+        assert_eq!(
+            fired.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+            vec![10, 20, 30]
+        );
+        assert!(fired
+            .iter()
+            .all(|(n, at)| *at >= now + Duration::from_millis(*n)));
+    }
+
+    #[test]
+    fn stop_drops_pending_timers() {
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        timer.schedule(Instant::now() + Duration::from_secs(60), move |_| {
+            tx.send(()).unwrap();
+        });
+        timer.stop();
+        // the sender is dropped with the timer instead of firing
+        assert!(rx.recv().is_err());
+    }
+}
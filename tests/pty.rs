@@ -0,0 +1,278 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    os::fd::FromRawFd,
+    process::Command,
+    sync::mpsc::{channel, Receiver},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use nix::pty::openpty;
+use termix::{
+    event::Event,
+    key::Key,
+    model::{Cmd, Model},
+    options::{InputSource, OutputSink, ProgramOptions},
+    Program, ProgramHandle, TermixError,
+};
+
+struct Counter(u32);
+
+impl Model<()> for Counter {
+    fn update(&mut self, event: &Event<()>) -> Option<Cmd<()>> {
+        match event {
+            Event::Keyboard(Key::Char('q')) => Some(Cmd::quit()),
+            Event::Keyboard(_) => {
+                self.0 += 1;
+                None
+            }
+            _ => None,
+        }
+    }
+    fn view(&self) -> String {
+        format!("{} keys pressed\n", self.0)
+    }
+}
+
+/// Collects what the program draws on the pty.
+fn read_screen(mut master: File) -> Receiver<u8> {
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        let mut buf = [0; 1024];
+        while let Ok(n @ 1..) = master.read(&mut buf) {
+            buf[..n].iter().for_each(|b| {
+                let _ = tx.send(*b);
+            });
+        }
+    });
+    rx
+}
+
+fn wait_for(screen: &Receiver<u8>, text: &str) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut seen = vec![];
+    // the text shows up first at the end of what has been seen
+    while !seen.ends_with(text.as_bytes()) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match screen.recv_timeout(left) {
+            Ok(b) => seen.push(b),
+            Err(_) => panic!(
+                "{text:?} was not drawn: {:?}",
+                String::from_utf8_lossy(&seen)
+            ),
+        }
+    }
+}
+
+/// Runs a counter on the slave side of the pty.
+fn start(slave: &File) -> JoinHandle<Result<Counter, TermixError>> {
+    let input = slave.try_clone().unwrap();
+    let output = slave.try_clone().unwrap();
+    thread::spawn(move || {
+        let options = ProgramOptions::new()
+            .input(InputSource::custom(input))
+            .output(OutputSink::custom(output));
+        Program::from_model_with_options(Counter(0), options).run()
+    })
+}
+
+#[test]
+fn run_on_pty() {
+    let pty = openpty(None, None).unwrap();
+    let mut master = unsafe { File::from_raw_fd(pty.master) };
+    let slave = unsafe { File::from_raw_fd(pty.slave) };
+    let screen = read_screen(master.try_clone().unwrap());
+    let program = start(&slave);
+    wait_for(&screen, "0 keys pressed");
+    master.write_all(b"ab").unwrap();
+    wait_for(&screen, "2 keys pressed");
+    master.write_all(b"q").unwrap();
+    let counter = program.join().unwrap().unwrap();
+    assert_eq!(counter.0, 2);
+}
+
+#[test]
+fn run_programs_back_to_back() {
+    let pty = openpty(None, None).unwrap();
+    let mut master = unsafe { File::from_raw_fd(pty.master) };
+    let slave = unsafe { File::from_raw_fd(pty.slave) };
+    let screen = read_screen(master.try_clone().unwrap());
+
+    let first = start(&slave);
+    wait_for(&screen, "0 keys pressed");
+    master.write_all(b"xq").unwrap();
+    assert_eq!(first.join().unwrap().unwrap().0, 1);
+
+    // every key goes to the second program, none to a leftover listener
+    let second = start(&slave);
+    wait_for(&screen, "0 keys pressed");
+    master.write_all(b"abc").unwrap();
+    wait_for(&screen, "3 keys pressed");
+    master.write_all(b"q").unwrap();
+    assert_eq!(second.join().unwrap().unwrap().0, 3);
+}
+
+/// View larger than the buffer of a pty, so writing it blocks until the screen is read.
+struct Large;
+
+impl Model<()> for Large {
+    fn update(&mut self, event: &Event<()>) -> Option<Cmd<()>> {
+        match event {
+            Event::Keyboard(Key::Char('q')) => Some(Cmd::quit()),
+            _ => None,
+        }
+    }
+    fn view(&self) -> String {
+        let line = format!("{}\n", "x".repeat(79));
+        format!("{}end of view\n", line.repeat(3000))
+    }
+}
+
+#[test]
+fn large_view_on_pty() {
+    let pty = openpty(None, None).unwrap();
+    let mut master = unsafe { File::from_raw_fd(pty.master) };
+    let slave = unsafe { File::from_raw_fd(pty.slave) };
+    let screen = read_screen(master.try_clone().unwrap());
+    // the input and the output share the open file description of the slave
+    let input = slave.try_clone().unwrap();
+    let output = slave.try_clone().unwrap();
+    let program = thread::spawn(move || {
+        let options = ProgramOptions::new()
+            .input(InputSource::custom(input))
+            .output(OutputSink::custom(output));
+        Program::from_model_with_options(Large, options).run()
+    });
+    wait_for(&screen, "end of view");
+    master.write_all(b"q").unwrap();
+    assert!(program.join().unwrap().is_ok());
+}
+
+struct Shell;
+
+impl Model<()> for Shell {
+    fn update(&mut self, event: &Event<()>) -> Option<Cmd<()>> {
+        match event {
+            Event::Keyboard(Key::Char('e')) => {
+                let mut command = Command::new("sh");
+                command.args(["-c", "echo from-child"]);
+                Some(Cmd::exec(command, |_| Event::Quit))
+            }
+            _ => None,
+        }
+    }
+    fn view(&self) -> String {
+        String::from("ready\n")
+    }
+}
+
+#[test]
+fn exec_on_pty() {
+    let pty = openpty(None, None).unwrap();
+    let mut master = unsafe { File::from_raw_fd(pty.master) };
+    let slave = unsafe { File::from_raw_fd(pty.slave) };
+    let screen = read_screen(master.try_clone().unwrap());
+    let input = slave.try_clone().unwrap();
+    let output = slave.try_clone().unwrap();
+    let program = thread::spawn(move || {
+        let options = ProgramOptions::new()
+            .input(InputSource::custom(input))
+            .output(OutputSink::custom(output));
+        Program::from_model_with_options(Shell, options).run()
+    });
+    wait_for(&screen, "ready");
+    master.write_all(b"e").unwrap();
+    // the process writes to the pty of the program, not to the stdout of the test
+    wait_for(&screen, "from-child");
+    assert!(program.join().unwrap().is_ok());
+}
+
+/// Lists the messages sent by `ProgramHandle`.
+struct Inbox(Vec<String>);
+
+impl Model<String> for Inbox {
+    fn update(&mut self, event: &Event<String>) -> Option<Cmd<String>> {
+        if let Event::Custom(msg) = event {
+            self.0.push(msg.clone());
+        }
+        None
+    }
+    fn view(&self) -> String {
+        format!("inbox: {}\n", self.0.join(", "))
+    }
+}
+
+/// Runs an inbox on the slave side of the pty, and returns its handle.
+fn start_inbox(
+    slave: &File,
+) -> (
+    ProgramHandle<String>,
+    JoinHandle<Result<Inbox, TermixError>>,
+) {
+    let input = slave.try_clone().unwrap();
+    let output = slave.try_clone().unwrap();
+    let (handle_tx, handle_rx) = channel();
+    let program = thread::spawn(move || {
+        let options = ProgramOptions::new()
+            .input(InputSource::custom(input))
+            .output(OutputSink::custom(output));
+        let program = Program::from_model_with_options(Inbox(vec![]), options);
+        handle_tx.send(program.sender()).unwrap();
+        program.run()
+    });
+    (handle_rx.recv().unwrap(), program)
+}
+
+#[test]
+fn handle_sends_from_another_thread() {
+    let pty = openpty(None, None).unwrap();
+    let master = unsafe { File::from_raw_fd(pty.master) };
+    let slave = unsafe { File::from_raw_fd(pty.slave) };
+    let screen = read_screen(master);
+    let (handle, program) = start_inbox(&slave);
+    wait_for(&screen, "inbox: ");
+    let sender = handle.clone();
+    thread::spawn(move || sender.send(String::from("hello")))
+        .join()
+        .unwrap();
+    wait_for(&screen, "inbox: hello");
+    handle.quit();
+    assert_eq!(program.join().unwrap().unwrap().0, ["hello"]);
+}
+
+#[test]
+fn handle_kills_the_program() {
+    let pty = openpty(None, None).unwrap();
+    let master = unsafe { File::from_raw_fd(pty.master) };
+    let slave = unsafe { File::from_raw_fd(pty.slave) };
+    let screen = read_screen(master);
+    let (handle, program) = start_inbox(&slave);
+    wait_for(&screen, "inbox: ");
+    handle.kill();
+    assert!(matches!(program.join().unwrap(), Err(TermixError::Killed)));
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn run_async_on_pty() {
+    let pty = openpty(None, None).unwrap();
+    let mut master = unsafe { File::from_raw_fd(pty.master) };
+    let slave = unsafe { File::from_raw_fd(pty.slave) };
+    let screen = read_screen(master.try_clone().unwrap());
+    let options = ProgramOptions::new()
+        .input(InputSource::custom(slave.try_clone().unwrap()))
+        .output(OutputSink::custom(slave));
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    // the program is a task of the runtime, which may move between its workers
+    let program = runtime.spawn(Program::from_model_with_options(Counter(0), options).run_async());
+    wait_for(&screen, "0 keys pressed");
+    master.write_all(b"ab").unwrap();
+    wait_for(&screen, "2 keys pressed");
+    master.write_all(b"q").unwrap();
+    let counter = runtime.block_on(program).unwrap().unwrap();
+    assert_eq!(counter.0, 2);
+}
@@ -0,0 +1,52 @@
+use termix::{
+    assert_frame_snapshot,
+    color::{Color, StyledText},
+    event::Event,
+    key::Key,
+    model::{Cmd, Model},
+    testing::{Sgr, TestProgram},
+};
+
+struct Menu {
+    choices: Vec<&'static str>,
+    cursor: usize,
+}
+
+impl Model<()> for Menu {
+    fn update(&mut self, event: &Event<()>) -> Option<Cmd<()>> {
+        match event {
+            Event::Keyboard(Key::Down) => {
+                self.cursor = (self.cursor + 1).min(self.choices.len() - 1)
+            }
+            Event::Keyboard(Key::Up) => self.cursor = self.cursor.saturating_sub(1),
+            _ => {}
+        }
+        None
+    }
+    fn view(&self) -> String {
+        let mut s = String::from("What to buy?\n\n");
+        for (i, choice) in self.choices.iter().enumerate() {
+            if i == self.cursor {
+                let line = format!("> {choice}");
+                s += &StyledText::new(&line, Some(Color::Ansi256(212)), None, None, None, None)
+                    .text();
+            } else {
+                s += &format!("  {choice}");
+            }
+            s += "\n";
+        }
+        s
+    }
+}
+
+#[test]
+fn menu_cursor() {
+    let mut program = TestProgram::new(Menu {
+        choices: vec!["carrots", "celery", "kohlrabi"],
+        cursor: 0,
+    });
+    assert_frame_snapshot!(program.last_frame());
+    program.key(Key::Down);
+    program.key(Key::Down);
+    assert_frame_snapshot!(program.last_frame(), Sgr::Keep);
+}